@@ -1,25 +1,15 @@
 #!/bin/bash
 
-echo "Trying to kill web_service process..."
-
-# Function to check if the process is still running
-is_process_running() {
-  pgrep -x "web_service" > /dev/null
-}
-
-# Function to kill the process
-kill_process() {
-  pkill -x "web_service"
-}
-
-# Try to kill the process
-kill_process
-
-# Check if the process is still running, and try to kill it again
-while is_process_running; do
-  echo "web_service is still running, trying to kill it again..."
-  kill_process
-  sleep 1
+for process in web_service dynamodb_stream; do
+  echo "Trying to kill $process process..."
+
+  # Try to kill the process, and again while it's still running
+  pkill -x "$process" || true
+  while pgrep -x "$process" > /dev/null; do
+    echo "$process is still running, trying to kill it again..."
+    pkill -x "$process" || true
+    sleep 1
+  done
+
+  echo "$process process killed successfully."
 done
-
-echo "web_service process killed successfully."
@@ -1,3 +1,4 @@
+use library::store::DynamoDbStore;
 use library::{activitypub, faas_snowflake_id};
 use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 
@@ -9,15 +10,14 @@ async fn main() {
     }
     let preferred_username = args[1].clone();
     let domain = args[2].clone();
-    let table_name = args[3].clone();
-    let table_name = table_name.as_str();
-    let db_client = library::dynamodb::get_client().await;
+    let store = DynamoDbStore::new(library::dynamodb::get_client().await, args[3].clone());
     let partition = format!("users/{preferred_username}/statuses");
     let node_id = faas_snowflake_id::get_node_id();
     let sort_value = faas_snowflake_id::get_id(node_id).to_string();
     let published_time = OffsetDateTime::now_utc();
     let status = activitypub::object::Object {
         actor: Some(format!("https://{domain}/users/{preferred_username}")),
+        assertion_method: None,
         atom_uri: None,
         attachment: Some(Vec::new()),
         attributed_to: None,
@@ -31,6 +31,7 @@ async fn main() {
         )),
         devices: None,
         discoverable: Some(false),
+        endpoints: None,
         followers: None,
         following: None,
         id: Some(format!(
@@ -58,13 +59,7 @@ async fn main() {
         url: Some(format!("https://{domain}/@{preferred_username}")),
         extra: serde_json::Value::Null,
     };
-    library::dynamodb::put_item(
-        &db_client,
-        table_name,
-        partition.as_str(),
-        &sort_value,
-        &status,
-    )
-    .await
-    .unwrap();
+    library::store::put(&store, partition.as_str(), &sort_value, &status)
+        .await
+        .unwrap();
 }
@@ -1,3 +1,5 @@
+use library::store::DynamoDbStore;
+
 #[tokio::main]
 async fn main() {
     let args: Vec<String> = std::env::args().collect();
@@ -7,9 +9,13 @@ async fn main() {
     let preferred_username = args[1].clone();
     let table_name = args[2].clone();
     let table_name = table_name.as_str();
-    let db_client = library::dynamodb::get_client().await;
+    let store = DynamoDbStore::new(library::dynamodb::get_client().await, table_name.to_owned());
     if let Ok(_url) = std::env::var("LOCAL_DYNAMODB_URL") {
-        library::dynamodb::create_table_if_not_exists(&db_client, table_name).await;
+        library::dynamodb::create_table_if_not_exists(store.client(), table_name)
+            .await
+            .unwrap();
     }
-    let _ = library::model::user::create(&db_client, table_name, preferred_username.as_str()).await;
+    library::model::user::create(&store, preferred_username.as_str())
+        .await
+        .unwrap();
 }
@@ -0,0 +1,289 @@
+use aws_lambda_events_extended::dynamodb::AttributeValue as StreamAttributeValue;
+use aws_sdk_dynamodbstreams::primitives::DateTime;
+use aws_sdk_dynamodbstreams::types::{
+    AttributeValue, OperationType, Record, ShardIteratorType, StreamViewType,
+};
+use aws_sdk_dynamodbstreams::Client;
+use lambda_runtime::Error;
+use library::store::Store;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tracing::{event, Level};
+
+const POLL_INTERVAL_MILLISECONDS: u64 = 1_000;
+/// Given to each handling of the records, like the timeout of the Lambda.
+const HANDLER_TIMEOUT_MILLISECONDS: u64 = 120_000;
+
+/// Runs the handler outside of Lambda, on the records in the JSON file at `path` when given,
+/// otherwise on the stream of the local `DynamoDB` table as it changes, or only on the queue
+/// with `SQLite`.
+///
+/// # Errors
+///
+/// Will return `Err` if it can't read the file or the stream.
+pub async fn run(path: Option<&str>) -> Result<(), Error> {
+    let store = library::store::from_env().await;
+    if let Some(path) = path {
+        return replay(path, store.as_ref()).await;
+    }
+
+    if std::env::var("SQLITE_DATABASE").is_ok() {
+        poll(store.as_ref()).await;
+    }
+
+    let url = std::env::var("LOCAL_DYNAMODB_URL")?;
+    let table_name = std::env::var("DYNAMODB_TABLE")?;
+    tail(&url, &table_name, store.as_ref()).await
+}
+
+/// Handles the stream records of the file once, the same way as a Lambda invocation.
+///
+/// The file has the body of the invocation, with the records in `Records`.
+async fn replay(path: &str, store: &dyn Store) -> Result<(), Error> {
+    let json_value: Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    let response = crate::handle(json_value, store, get_deadline()).await?;
+    println!("{response}");
+    Ok(())
+}
+
+/// Handles the due fan-outs and deliveries at every interval, as `SQLite` has no stream.
+async fn poll(store: &dyn Store) -> ! {
+    event!(Level::INFO, "Polling the delivery queue");
+    loop {
+        handle(json!({}), store).await;
+        tokio::time::sleep(Duration::from_millis(POLL_INTERVAL_MILLISECONDS)).await;
+    }
+}
+
+/// Polls the shards of the table's stream, handling the new records together with the due
+/// fan-outs and deliveries, which on AWS the delivery schedule takes care of.
+async fn tail(url: &str, table_name: &str, store: &dyn Store) -> Result<(), Error> {
+    let client = get_client(url).await;
+    let streams = client.list_streams().table_name(table_name).send().await?;
+    let stream_arn = streams
+        .streams()
+        .and_then(|streams| streams.first())
+        .and_then(|stream| stream.stream_arn())
+        .ok_or_else(|| library::Error::NotFound(format!("stream of {table_name}")))?
+        .to_owned();
+    event!(Level::INFO, "Tailing {stream_arn}");
+    // the records written before starting are skipped, those of new shards aren't
+    let mut iterators = HashMap::new();
+    let mut iterator_type = ShardIteratorType::Latest;
+    loop {
+        add_shards(&client, &stream_arn, iterator_type, &mut iterators).await?;
+        iterator_type = ShardIteratorType::TrimHorizon;
+        let mut records = Vec::new();
+        for iterator in iterators.values_mut() {
+            // closed shards are kept without iterator, so that they aren't read again
+            let Some(shard_iterator) = iterator.take() else {
+                continue;
+            };
+            let output = client
+                .get_records()
+                .shard_iterator(shard_iterator)
+                .send()
+                .await?;
+            for record in output.records().unwrap_or_default() {
+                match to_event_record(record, &stream_arn) {
+                    Ok(record) => records.push(record),
+                    Err(err) => event!(Level::WARN, "Skipping stream record: {err}"),
+                }
+            }
+            *iterator = output.next_shard_iterator().map(ToOwned::to_owned);
+        }
+
+        let json_value = if records.is_empty() {
+            json!({})
+        } else {
+            json!({ "Records": records })
+        };
+        handle(json_value, store).await;
+        tokio::time::sleep(Duration::from_millis(POLL_INTERVAL_MILLISECONDS)).await;
+    }
+}
+
+/// Handles the invocation, logging what failed instead of stopping, as a Lambda would be
+/// invoked again.
+async fn handle(json_value: Value, store: &dyn Store) {
+    match crate::handle(json_value, store, get_deadline()).await {
+        Ok(response)
+            if response["batchItemFailures"]
+                .as_array()
+                .is_some_and(|failures| !failures.is_empty()) =>
+        {
+            event!(Level::ERROR, "Failed stream records: {response}");
+        }
+        Ok(_) => {}
+        Err(err) => event!(Level::ERROR, "Handling: {err}"),
+    }
+}
+
+async fn get_client(url: &str) -> Client {
+    let config = aws_config::from_env().region("us-east-1").load().await;
+    let streams_config = aws_sdk_dynamodbstreams::config::Builder::from(&config)
+        .endpoint_url(url)
+        .build();
+    Client::from_conf(streams_config)
+}
+
+async fn add_shards(
+    client: &Client,
+    stream_arn: &str,
+    iterator_type: ShardIteratorType,
+    iterators: &mut HashMap<String, Option<String>>,
+) -> Result<(), Error> {
+    let stream = client
+        .describe_stream()
+        .stream_arn(stream_arn)
+        .send()
+        .await?;
+    let shards = stream
+        .stream_description()
+        .and_then(|description| description.shards())
+        .unwrap_or_default();
+    for shard_id in shards.iter().filter_map(|shard| shard.shard_id()) {
+        if iterators.contains_key(shard_id) {
+            continue;
+        }
+
+        let output = client
+            .get_shard_iterator()
+            .stream_arn(stream_arn)
+            .shard_id(shard_id)
+            .shard_iterator_type(iterator_type.clone())
+            .send()
+            .await?;
+        iterators.insert(
+            shard_id.to_owned(),
+            output.shard_iterator().map(ToOwned::to_owned),
+        );
+    }
+    Ok(())
+}
+
+fn get_deadline() -> u64 {
+    let now = OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000;
+    u64::try_from(now).unwrap_or_default() + HANDLER_TIMEOUT_MILLISECONDS
+}
+
+/// The record as the Lambda receives it from the stream.
+fn to_event_record(record: &Record, stream_arn: &str) -> library::Result<Value> {
+    let stream_record = record
+        .dynamodb()
+        .ok_or_else(|| library::Error::Parse("record without dynamodb".to_owned()))?;
+    let mut dynamodb = json!({
+        "ApproximateCreationDateTime": stream_record
+            .approximate_creation_date_time()
+            .map(DateTime::as_secs_f64),
+        "SequenceNumber": stream_record.sequence_number(),
+        "SizeBytes": stream_record.size_bytes().unwrap_or_default(),
+        "StreamViewType": stream_record
+            .stream_view_type()
+            .map(StreamViewType::as_str),
+    });
+    if let Some(image) = stream_record.new_image() {
+        dynamodb["NewImage"] = serde_json::to_value(to_stream_image(image)?)?;
+    }
+    if let Some(image) = stream_record.old_image() {
+        dynamodb["OldImage"] = serde_json::to_value(to_stream_image(image)?)?;
+    }
+    Ok(json!({
+        "awsRegion": record.aws_region(),
+        "dynamodb": dynamodb,
+        "eventID": record.event_id(),
+        "eventName": record.event_name().map(OperationType::as_str),
+        "eventSource": record.event_source(),
+        "eventSourceARN": stream_arn,
+        "eventVersion": record.event_version(),
+    }))
+}
+
+fn to_stream_image(
+    image: &HashMap<String, AttributeValue>,
+) -> library::Result<library::stream::StreamImage> {
+    image
+        .iter()
+        .map(|(name, value)| Ok((name.clone(), to_stream_value(value)?)))
+        .collect()
+}
+
+fn to_stream_value(value: &AttributeValue) -> library::Result<StreamAttributeValue> {
+    let mut stream_value = StreamAttributeValue::default();
+    match value {
+        AttributeValue::B(b) => stream_value.b = Some(b.as_ref().to_vec().into()),
+        AttributeValue::Bool(bool) => stream_value.bool = Some(*bool),
+        AttributeValue::Bs(bs) => {
+            stream_value.bs = Some(bs.iter().map(|b| b.as_ref().to_vec().into()).collect());
+        }
+        AttributeValue::L(l) => {
+            stream_value.l = Some(
+                l.iter()
+                    .map(to_stream_value)
+                    .collect::<library::Result<_>>()?,
+            );
+        }
+        AttributeValue::M(m) => stream_value.m = Some(to_stream_image(m)?),
+        AttributeValue::N(n) => stream_value.n = Some(n.clone()),
+        AttributeValue::Ns(ns) => stream_value.ns = Some(ns.clone()),
+        AttributeValue::Null(null) => stream_value.null = Some(*null),
+        AttributeValue::S(s) => stream_value.s = Some(s.clone()),
+        AttributeValue::Ss(ss) => stream_value.ss = Some(ss.clone()),
+        _ => return Err(library::Error::Parse("unknown attribute value".to_owned())),
+    }
+    Ok(stream_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_lambda_events_extended::dynamodb::{DynamoDBEventRecord, DynamoDBOperationType};
+    use aws_sdk_dynamodbstreams::types::StreamRecord;
+
+    #[test]
+    fn test_to_event_record() {
+        // Arrange
+        let record = Record::builder()
+            .aws_region("ddblocal")
+            .event_id("1")
+            .event_name(OperationType::Remove)
+            .event_source("aws:dynamodb")
+            .event_version("1.1")
+            .dynamodb(
+                StreamRecord::builder()
+                    .approximate_creation_date_time(DateTime::from_secs(1_674_086_400))
+                    .old_image(
+                        "partition_key",
+                        AttributeValue::S("users/a/statuses".to_owned()),
+                    )
+                    .old_image("tag", AttributeValue::L(vec![AttributeValue::Null(true)]))
+                    .sequence_number("100")
+                    .size_bytes(50)
+                    .stream_view_type(StreamViewType::NewAndOldImages)
+                    .build(),
+            )
+            .build();
+
+        // Act
+        let actual =
+            to_event_record(&record, "arn:aws:dynamodb:ddblocal:000000000000:table/t").unwrap();
+
+        // Assert
+        let actual: DynamoDBEventRecord = serde_json::from_value(actual).unwrap();
+        assert_eq!(actual.event_name, DynamoDBOperationType::Remove);
+        assert_eq!(actual.dynamodb.sequence_number, "100");
+        assert_eq!(
+            actual.dynamodb.approximate_creation_date_time,
+            Some(1_674_086_400.0)
+        );
+        assert!(actual.dynamodb.new_image.is_none());
+        let old_image = actual.dynamodb.old_image.unwrap();
+        assert_eq!(
+            old_image["partition_key"].s.as_deref(),
+            Some("users/a/statuses")
+        );
+        assert_eq!(old_image["tag"].l.as_ref().unwrap()[0].null, Some(true));
+    }
+}
@@ -1,189 +1,365 @@
-use aws_lambda_events_extended::dynamodb::DynamoDBEvent;
-use aws_sdk_dynamodb::types::AttributeValue as AttributeValue2;
-use http::header::{HeaderMap, HeaderValue};
+use aws_lambda_events_extended::dynamodb::{
+    DynamoDBEvent, DynamoDBEventRecord, DynamoDBOperationType,
+};
+use http::header::HeaderMap;
 use lambda_runtime::{service_fn, Error, LambdaEvent};
 use library::{
-    activitypub::object::Object,
+    activitypub::{collection::Cursor, object::Object},
     dynamodb,
-    model::{self, user::User},
+    model::{
+        self, actor,
+        delivery::{self, Delivery, FanOut},
+        follower,
+        user::User,
+    },
+    store::{DynamoDbStore, Store},
 };
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use time::OffsetDateTime;
 use tracing::{event, Level};
 
-const METHOD: &str = "POST";
+const DELIVERY_BATCH_SIZE: usize = 50;
+const FAN_OUT_BATCH_SIZE: usize = 10;
+const FAN_OUT_CHUNK_SIZE: usize = 100;
+/// Left for the chunk of work in progress when no new one is started.
+const DEADLINE_MARGIN_MILLISECONDS: u64 = 20_000;
+
+mod local;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     library::trace::init();
+    if std::env::var("AWS_LAMBDA_RUNTIME_API").is_err() {
+        return local::run(std::env::args().nth(1).as_deref()).await;
+    }
+
     let func = service_fn(func);
     lambda_runtime::run(func).await?;
     Ok(())
 }
 
 async fn func(event: LambdaEvent<Value>) -> Result<Value, Error> {
-    let (event, _context) = event.into_parts();
+    let (event, context) = event.into_parts();
     let json_value = serde_json::to_value(event)?;
+    let store = DynamoDbStore::new(
+        dynamodb::get_client().await,
+        std::env::var("DYNAMODB_TABLE").unwrap(),
+    );
+    handle(json_value, &store, context.deadline).await
+}
 
-    let dynamodb_event: DynamoDBEvent = serde_json::from_value(json_value)?;
-
-    let db_client = dynamodb::get_client().await;
-    let http_client = reqwest::Client::new();
+/// Handles an invocation by the stream or by the delivery schedule, working until `deadline`,
+/// in milliseconds since the epoch.
+async fn handle(json_value: Value, store: &dyn Store, deadline: u64) -> Result<Value, Error> {
+    let http_client = library::http_client::get()?;
     let domain_name = std::env::var("CUSTOM_DOMAIN").unwrap();
-    let table_name = std::env::var("DYNAMODB_TABLE").unwrap();
-    for record in dynamodb_event.records {
-        if let Some(new_image) = record.dynamodb.new_image {
-            let partition = new_image
-                .get(dynamodb::PARTITION_KEY_NAME)
-                .and_then(|v| v.s.clone())
-                .unwrap();
-            event!(Level::DEBUG, "New item with ID: {partition}");
-            let split_partition = partition.split('/').collect::<Vec<&str>>();
-            if split_partition.len() != 3 {
-                continue;
-            }
+    let horizon_seconds = std::env::var("DELIVERY_HORIZON_SECONDS")
+        .ok()
+        .and_then(|seconds| seconds.parse().ok())
+        .unwrap_or(delivery::DEFAULT_HORIZON_SECONDS);
+    let now = OffsetDateTime::now_utc().unix_timestamp().unsigned_abs();
+    // the delivery schedule invokes the function without records, only to continue the work
+    let failed_sequence_numbers = if json_value.get("Records").is_some() {
+        let mut dynamodb_event: DynamoDBEvent = serde_json::from_value(json_value)?;
+        // the queue changes with every attempt, which must not start more attempts
+        dynamodb_event
+            .records
+            .retain(|record| !get_partition(record).is_some_and(delivery::is_delivery_partition));
+        if dynamodb_event.records.is_empty() {
+            return Ok(get_response(&[]));
+        }
 
-            if split_partition[2] != "statuses" {
-                continue;
-            }
+        process_records(dynamodb_event.records, now, store, &domain_name).await
+    } else {
+        Vec::new()
+    };
 
-            event!(Level::DEBUG, "dynamodb_event_to_map: {new_image:?}");
-            let status = dynamodb_event_to_map(new_image);
-            let status: Object = serde_dynamo::from_item(status).unwrap();
-            let username = split_partition[1];
-            let signature_key_id = format!("https://{domain_name}/users/{username}#main-key");
-            let get_item_output = model::user::get_item(username, &db_client, &table_name).await;
-            let item = get_item_output.item.unwrap();
-            let user: User = serde_dynamo::from_item(item).unwrap();
-
-            let followers_partition = format!("users/{username}/followers");
-            let response = db_client
-                .query()
-                .table_name(&table_name)
-                .key_condition_expression("#partition_key = :valueToMatch")
-                .expression_attribute_names(
-                    "#partition_key",
-                    dynamodb::PARTITION_KEY_NAME.to_owned(),
-                )
-                .expression_attribute_values(
-                    ":valueToMatch",
-                    AttributeValue2::S(followers_partition),
-                )
-                .limit(20)
-                .scan_index_forward(false)
-                .send()
-                .await
-                .unwrap();
-            let items = response.items().unwrap();
-            let followers: Vec<Object> = serde_dynamo::from_items(items.to_vec()).unwrap();
-            for follower in followers {
-                let (url, request_body, headers) = get_notification(
-                    &status,
-                    &user,
-                    signature_key_id.as_str(),
-                    &follower,
-                    &OffsetDateTime::UNIX_EPOCH,
-                );
-                event!(
-                    Level::DEBUG,
-                    "curl -H '{}' -d '{}' {}",
-                    headers
-                        .clone()
-                        .into_iter()
-                        .map(|(key, value)| format!(
-                            "{}: {}",
-                            key.unwrap(),
-                            value.to_str().unwrap()
-                        ))
-                        .collect::<Vec<_>>()
-                        .join("' -H '"),
-                    request_body,
-                    url
-                );
-                http_client
-                    .post(url)
-                    .body(request_body)
-                    .headers(headers.clone())
-                    .send()
-                    .await
-                    .unwrap();
-            }
+    // the records are already saved as fan-outs, which the next invocation continues
+    if let Err(err) = fan_out(store, now, deadline).await {
+        event!(Level::ERROR, "Fan-out: {err}");
+    }
+    if let Err(err) = deliver_due(store, http_client, now, horizon_seconds, deadline).await {
+        event!(Level::ERROR, "Delivery: {err}");
+    }
+    Ok(get_response(&failed_sequence_numbers))
+}
+
+/// Starts the fan-out of each record on its own, returning the sequence numbers of those that
+/// failed, so that the stream retries only them instead of the whole batch.
+async fn process_records(
+    records: Vec<DynamoDBEventRecord>,
+    now: u64,
+    store: &dyn Store,
+    domain_name: &str,
+) -> Vec<String> {
+    let mut failed_sequence_numbers = Vec::new();
+    for record in records {
+        let sequence_number = record.dynamodb.sequence_number.clone();
+        if let Err(err) = process_record(record, now, store, domain_name).await {
+            event!(Level::ERROR, "Record {sequence_number}: {err}");
+            failed_sequence_numbers.push(sequence_number);
         }
     }
+    failed_sequence_numbers
+}
 
-    Ok(json!({ "message": "Success" }))
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+async fn process_record(
+    record: DynamoDBEventRecord,
+    now: u64,
+    store: &dyn Store,
+    domain_name: &str,
+) -> library::Result<()> {
+    let changed_unix_time_seconds = record
+        .dynamodb
+        .approximate_creation_date_time
+        .map_or(now, |seconds| seconds as u64);
+    // removed items only have the old image
+    let Some(image) = record.dynamodb.new_image.or(record.dynamodb.old_image) else {
+        return Ok(());
+    };
+    start_fan_out(
+        &record.event_name,
+        image,
+        changed_unix_time_seconds,
+        store,
+        domain_name,
+    )
+    .await
 }
 
-fn dynamodb_event_to_map(
-    stream: HashMap<String, aws_lambda_events_extended::dynamodb::AttributeValue>,
-) -> HashMap<String, AttributeValue2> {
-    let mut items = HashMap::new();
-    for (key, value) in stream {
-        items.insert(key, convert(&value).clone());
-    }
-    items
+fn get_partition(record: &DynamoDBEventRecord) -> Option<&str> {
+    [
+        &record.dynamodb.keys,
+        &record.dynamodb.new_image,
+        &record.dynamodb.old_image,
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|image| image.get(dynamodb::PARTITION_KEY_NAME)?.s.as_deref())
 }
 
-fn convert(value: &aws_lambda_events_extended::dynamodb::AttributeValue) -> AttributeValue2 {
-    if let Some(bool) = &value.bool {
-        return AttributeValue2::Bool(*bool);
-    }
-    if let Some(l) = &value.l {
-        return AttributeValue2::L(l.iter().map(convert).collect::<Vec<_>>());
+/// The partial batch response of a Lambda consuming a stream.
+fn get_response(failed_sequence_numbers: &[String]) -> Value {
+    let batch_item_failures = failed_sequence_numbers
+        .iter()
+        .map(|sequence_number| json!({ "itemIdentifier": sequence_number }))
+        .collect::<Vec<_>>();
+    json!({ "batchItemFailures": batch_item_failures })
+}
+
+/// Whether there is time left before the invocation deadline, in milliseconds since the epoch,
+/// to start another chunk of work.
+fn has_time(deadline: u64) -> bool {
+    let now = OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000;
+    now + i128::from(DEADLINE_MARGIN_MILLISECONDS) < i128::from(deadline)
+}
+
+async fn start_fan_out(
+    operation: &DynamoDBOperationType,
+    image: library::stream::StreamImage,
+    changed_unix_time_seconds: u64,
+    store: &dyn Store,
+    domain_name: &str,
+) -> library::Result<()> {
+    let partition = image
+        .get(dynamodb::PARTITION_KEY_NAME)
+        .and_then(|v| v.s.clone())
+        .ok_or_else(|| library::Error::Parse("item without partition key".to_owned()))?;
+    event!(Level::DEBUG, "{operation:?} of item with ID: {partition}");
+    let split_partition = partition.split('/').collect::<Vec<&str>>();
+    if split_partition.len() != 3 {
+        return Ok(());
     }
-    if let Some(m) = &value.m {
-        return AttributeValue2::M(dynamodb_event_to_map(m.clone()));
+
+    if split_partition[2] != "statuses" {
+        return Ok(());
     }
-    if let Some(ns) = &value.ns {
-        return AttributeValue2::Ns(ns.clone());
+
+    event!(Level::DEBUG, "from_stream_image: {image:?}");
+    let status = library::stream::from_stream_image(image)?;
+    let status: Object = serde_dynamo::from_item(status)?;
+    let activity = get_activity(operation, &status, changed_unix_time_seconds)?;
+    let username = split_partition[1];
+    let signature_key_id = format!("https://{domain_name}/users/{username}#main-key");
+    let fan_out = get_fan_out(&activity, username, &signature_key_id);
+    delivery::put_fan_out(store, &fan_out).await
+}
+
+/// Queues the deliveries of the statuses being fanned out, a chunk of followers at a time,
+/// saving the progress so that the next invocation continues where this one stopped.
+async fn fan_out(store: &dyn Store, now: u64, deadline: u64) -> library::Result<()> {
+    for mut fan_out in delivery::get_fan_outs(store, FAN_OUT_BATCH_SIZE).await? {
+        // servers that verify Ed25519 signatures get them from the users that have the key
+        let has_ed25519_key = model::user::load(&fan_out.username, store)
+            .await?
+            .is_some_and(|user| user.ed25519_private_key.is_some());
+        loop {
+            if !has_time(deadline) {
+                return Ok(());
+            }
+
+            let cursor = fan_out
+                .cursor
+                .as_deref()
+                .map_or(Cursor::First, Cursor::After);
+            let page =
+                follower::load_page(&fan_out.username, cursor, FAN_OUT_CHUNK_SIZE, store).await?;
+            for (inbox, ed25519) in get_inboxes(&page.items, store).await {
+                let mut delivery = fan_out.delivery(&inbox, now);
+                delivery.signature_key_id = model::user::get_signature_key_id(
+                    &fan_out.signature_key_id,
+                    ed25519 && has_ed25519_key,
+                );
+                delivery::enqueue(store, &delivery).await?;
+            }
+            let Some(next) = page.next else {
+                delivery::delete_fan_out(store, &fan_out).await?;
+                break;
+            };
+            // another invocation is going through the same followers
+            if !delivery::advance_fan_out(store, &mut fan_out, next).await? {
+                event!(Level::DEBUG, "Fan-out of {} moved on", fan_out.activity_id);
+                break;
+            }
+        }
     }
-    if let Some(s) = &value.s {
-        return AttributeValue2::S(s.clone());
+    Ok(())
+}
+
+/// Followers on the same server share its inbox, so that it receives the status once,
+/// signed with Ed25519 when any of them shows that the server verifies it.
+async fn get_inboxes(actor_ids: &[String], store: &dyn Store) -> BTreeMap<String, bool> {
+    let mut inboxes = BTreeMap::new();
+    for actor_id in actor_ids {
+        match actor::get_delivery_inbox(actor_id, store).await {
+            Ok((inbox, ed25519)) => {
+                *inboxes.entry(inbox).or_default() |= ed25519;
+            }
+            Err(err) => event!(Level::WARN, "Not delivering to follower {actor_id}: {err}"),
+        }
     }
-    if let Some(ss) = &value.ss {
-        return AttributeValue2::Ss(ss.clone());
+    inboxes
+}
+
+/// Attempts the deliveries that are due, leaving the failed ones to the queue.
+async fn deliver_due(
+    store: &dyn Store,
+    http_client: &reqwest::Client,
+    now: u64,
+    horizon_seconds: u64,
+    deadline: u64,
+) -> library::Result<()> {
+    for mut delivery in delivery::get_due(store, now, DELIVERY_BATCH_SIZE).await? {
+        if !has_time(deadline) {
+            return Ok(());
+        }
+
+        // another invocation may be attempting it already
+        if !delivery::claim(store, &mut delivery, now).await? {
+            continue;
+        }
+
+        if delivery::get_state(store, &delivery).await? == Some(delivery::State::Delivered) {
+            delivery::discard(store, &delivery).await?;
+            continue;
+        }
+
+        let result = deliver(store, http_client, &delivery).await;
+        if let Err(err) = &result {
+            event!(
+                Level::WARN,
+                "Delivery of {} to {}: {err}",
+                delivery.activity_id,
+                delivery.inbox
+            );
+        }
+        delivery::complete(store, &delivery, &result, now, horizon_seconds).await?;
     }
+    Ok(())
+}
 
-    panic!("empty attribute value");
+async fn deliver(
+    store: &dyn Store,
+    http_client: &reqwest::Client,
+    delivery: &Delivery,
+) -> library::Result<()> {
+    let user = model::user::load(&delivery.username, store)
+        .await?
+        .ok_or_else(|| library::Error::NotFound(format!("user {}", delivery.username)))?;
+    let headers = get_headers(delivery, &user, &OffsetDateTime::UNIX_EPOCH)?;
+    event!(
+        Level::DEBUG,
+        "curl -H '{}' -d '{}' {}",
+        headers
+            .iter()
+            .map(|(key, value)| format!("{key}: {}", value.to_str().unwrap_or_default()))
+            .collect::<Vec<_>>()
+            .join("' -H '"),
+        delivery.body,
+        delivery.inbox
+    );
+    library::activitypub::delivery::post(
+        http_client,
+        &delivery.inbox,
+        delivery.body.clone(),
+        headers,
+    )
+    .await
 }
 
-fn get_notification(
+/// Followers get the same `Create` of a new status that the outbox serves,
+/// an `Update` when it's edited and a `Delete` when it's removed.
+fn get_activity(
+    operation: &DynamoDBOperationType,
     status: &Object,
+    changed_unix_time_seconds: u64,
+) -> library::Result<Value> {
+    match operation {
+        DynamoDBOperationType::Insert => library::activitypub::activity::create(status),
+        DynamoDBOperationType::Modify => {
+            library::activitypub::activity::update(status, changed_unix_time_seconds)
+        }
+        DynamoDBOperationType::Remove => library::activitypub::activity::delete(status),
+    }
+}
+
+fn get_fan_out(activity: &Value, username: &str, signature_key_id: &str) -> FanOut {
+    FanOut {
+        username: username.to_owned(),
+        activity_id: activity["id"].as_str().unwrap_or_default().to_owned(),
+        body: activity.to_string(),
+        signature_key_id: signature_key_id.to_owned(),
+        cursor: None,
+    }
+}
+
+/// Signs the delivery at the time of the attempt, as inboxes reject old signatures.
+fn get_headers(
+    delivery: &Delivery,
     user: &User,
-    signature_key_id: &str,
-    follower: &Object,
     time_provider: &dyn library::time_provider::TimeProvider,
-) -> (String, String, HeaderMap) {
-    let url = follower.actor.as_ref().unwrap();
-    let url = format!("{url}/inbox");
-    let split_url = url.splitn(4, '/').collect::<Vec<&str>>();
-    let path = format!("/{}", split_url[3]);
-    let status = status.clone();
-    let request_body = serde_json::to_string(&status).unwrap();
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "Content-Type",
-        HeaderValue::from_str("application/activity+json").unwrap(),
-    );
-    headers.insert("Host", HeaderValue::from_str(split_url[2]).unwrap());
-    library::activitypub::request::sign(
-        METHOD,
-        path.as_str(),
-        &mut headers,
-        &request_body,
-        user.private_key.as_ref().unwrap(),
-        signature_key_id,
+) -> library::Result<HeaderMap> {
+    let private_key = user
+        .get_private_key(&delivery.signature_key_id)
+        .ok_or_else(|| library::Error::NotFound("private key".to_owned()))?;
+    library::activitypub::delivery::get_headers(
+        &delivery.inbox,
+        &delivery.body,
+        private_key,
+        &delivery.signature_key_id,
         time_provider,
-    );
-    (url, request_body, headers)
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use base64::{engine::general_purpose, Engine as _};
+    use http::header::HeaderValue;
     use library::activitypub;
+    use library::store::MemoryStore;
     use rsa::pkcs1::DecodeRsaPrivateKey;
     use rsa::pkcs1::EncodeRsaPublicKey;
     use rsa::RsaPrivateKey;
@@ -196,120 +372,19 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_dynamodb_event_to_map() {
-        // Arrange
-        let mut context =
-            HashMap::<String, aws_lambda_events_extended::dynamodb::AttributeValue>::new();
-        context.insert(
-            "PropertyValue".to_owned(),
-            aws_lambda_events_extended::dynamodb::AttributeValue {
-                b: None,
-                bool: None,
-                bs: None,
-                l: None,
-                m: None,
-                n: None,
-                ns: None,
-                null: None,
-                s: Some("schema:PropertyValue".to_owned()),
-                ss: None,
-            },
-        );
-        let mut stream =
-            HashMap::<String, aws_lambda_events_extended::dynamodb::AttributeValue>::new();
-        stream.insert(
-            "partition_key".to_owned(),
-            aws_lambda_events_extended::dynamodb::AttributeValue {
-                b: None,
-                bool: None,
-                bs: None,
-                l: None,
-                m: None,
-                n: None,
-                ns: None,
-                null: None,
-                s: Some("users/sample_user/statuses".to_owned()),
-                ss: None,
-            },
-        );
-        stream.insert(
-            "@context".to_owned(),
-            aws_lambda_events_extended::dynamodb::AttributeValue {
-                b: None,
-                bool: None,
-                bs: None,
-                l: Some(vec![
-                    aws_lambda_events_extended::dynamodb::AttributeValue {
-                        b: None,
-                        bool: None,
-                        bs: None,
-                        l: None,
-                        m: None,
-                        n: None,
-                        ns: None,
-                        null: None,
-                        s: Some("https://www.w3.org/ns/activitystreams".to_owned()),
-                        ss: None,
-                    },
-                    aws_lambda_events_extended::dynamodb::AttributeValue {
-                        b: None,
-                        bool: None,
-                        bs: None,
-                        l: None,
-                        m: Some(context),
-                        n: None,
-                        ns: None,
-                        null: None,
-                        s: None,
-                        ss: None,
-                    },
-                ]),
-                m: None,
-                n: None,
-                ns: None,
-                null: None,
-                s: None,
-                ss: None,
-            },
-        );
-        let mut expected_context = HashMap::<String, AttributeValue2>::new();
-        expected_context.insert(
-            "PropertyValue".to_string(),
-            AttributeValue2::S("schema:PropertyValue".to_owned()),
-        );
-        let mut expected = HashMap::<String, AttributeValue2>::new();
-        expected.insert(
-            "partition_key".to_owned(),
-            AttributeValue2::S("users/sample_user/statuses".to_owned()),
-        );
-        expected.insert(
-            "@context".to_owned(),
-            AttributeValue2::L(vec![
-                AttributeValue2::S("https://www.w3.org/ns/activitystreams".to_owned()),
-                AttributeValue2::M(expected_context),
-            ]),
-        );
-
-        // Act
-        let actual = dynamodb_event_to_map(stream);
-
-        // Assert
-        assert_eq!(expected, actual);
-    }
-
-    #[test]
-    fn test_notify_follower() {
+    #[tokio::test]
+    async fn test_notify_follower() {
         // Arrange
         let domain = "example.com";
         let username = "test_username";
         let sort_value = 1234567890;
         let status = Object {
-            actor: None,
+            actor: Some(format!("https://{domain}/users/{username}")),
+            assertion_method: None,
             atom_uri: None,
             attachment: Some(Vec::new()),
             attributed_to: None,
-            cc: None,
+            cc: Some(vec![format!("https://{domain}/users/{username}/followers")]),
             content: Some("test content".to_string()),
             context: activitypub::context::default(),
             conversation: Some(format!(
@@ -317,6 +392,7 @@ mod tests {
             )),
             devices: None,
             discoverable: Some(false),
+            endpoints: None,
             followers: None,
             following: None,
             id: Some(format!(
@@ -348,7 +424,13 @@ mod tests {
         .decode("MIIJKQIBAAKCAgEAySGRDkOp73hbsGQxujwMypbLnr1Z0uZD4l6+ml8ZyR/p8mHixFHGOuZIWJ5k17oxs8/FkYPxEY9XFwzSl09HJCkEWyNsX83Ytp9FRDnfrQO85DRLDAzlDVJtUWPWMNLEfMNklGeypQcG5db5Lwggp1u8eVlfxLkR3jC1roUGluuc4C32/6CLx4UpFIt2/EdegE2ODFV5/NmW8b7Fh32YQoihs7V96izwutwj6lt+7feUdWRIL5mm7t4Vcza4b5DLIQgpCBLltDKr5MGmaCY7BZaGzhB3ZY/6EBWurf92LVsR9hlY5XbawDEowub2QLFA9rju+Qg19v5JDtz62dq3B+29CEXxg8Fw50gt+6kh2Ncz6Cbza76usKmxmE2kY8dmsJAAhWDjO19rSxFrNQ9ANw3CE8teNfaEAWR6fCu7pmo393a7hjBbUxTp/CzIJ/ofyTgRHDwcBveprcZS4qqI5RMtrtY/FJ2zMJzm5VHALJWBIJEFuui0/+2jxmKiMN4mecdZAW3y83WhAtTGwpEwJ7vTHGAk+cRf2bRyslCJ5UCWUrqaDQFxnm9rNpvqO7S4vfuNzPuapwiJcLgjrYmPQRjcj9sR9BS+iMWU8GJO336oebc8aCwU5/0Rt6sgXtxtZNsW+kl0YMYzI2sEVnd42tExrniuY22a71/rj+8xwVcCAwEAAQKCAgBMaUY0hxxOcAlVcBs8R4gMh1GAUyuG5hgwLhJ3j126fTdh8DI4p2CKC+a8VCC3nHM5ftvuNpQlObG6fhKbjXDXmgWfokuP8iI87zFfhCUoE911TTCduWBjuUbyvt0m20vuokTZ5LOH4q2KMCum5I2TR1TJPV0W3cCeCx9a2Ary1zxYJt5Jq3KvMDW1Km7f1TVfxRcMNIUNvJSN7w4YNWzdCg90uKTHjJ9APlYeuPf17DMojhqmitdStGitxsI3EGk7eWAtQxClbwLC+5b+xldx/gfkzXiuyw4TgklErWL2RD0EpAiT1J2ymnqD4T74wN6PXR7c2XO3DFAxJ17d2SvjWIzt5wR9hRZDIksNngnOClR0hnaiBdQewsE4XVUxWGtEhQmbAn3KQ71FuHItLPvdzbUo/bsgB2gOl7Oqr4uwJ5CptVCzLmN1+BXmSQzMmg/ga30ibZyBQwZfpZ6HmV9be0bglmDq6vc8o7mIODY8srHniau5J0QiwvRFTE7kshBvV70BN/SL+agb1LYAnLl1p5851m3QQTV/6MWJhsCZo6nJtiUni4clOEzyBCoggeKoC8auUyvacd3rHrKsqFXEI68ikrbl7DwoJcfAYmqB6vU4HN49WzyUH14A1IZ4wwpbPsqZXoqV6hzdKOM7gYFzwk3KUQqdG5cH3AJ8/fd7cQKCAQEA8AI5fxXQ2vUiWAlbyaoip/+5mnXNo668WP+8iKj8OW/tj98GR+WU8ax4oPk1PER+y4bpvxpk6BkR23hBbIpHwJCQ78591TG0Cyxmbiymson43KyhiNMhzlG508/Hmw0lAb0sdL3ftfqTM/OEXRAlPaGk+621ZxbZ2XDpdnrSFNgx3F0DX+jTdvmfKfW92bfW/up7CC8aYaxK0QKFZlLa9rlRAJSBAf6Jzgpd5M9pn3bUEzuobHl43r6SBeyZlmE/WMTucH7QuxWK03ndMTRYhCc5EH5r8wh/WlUgjGPUhn1IHaRFaHOpG8+osTCH8gUL/W2+ibBJlwjM7pqG+A/k3QKCAQEA1og3QzQ90Fag7MoKm+FJMb+ocTDD6vDFqtOXorTiSK2GJhcsQ+ZC3PWxK3RY9OxuxIjeODq1k9XeM8oLf8Uampe8/5x6jouQPJiwgk2NA0Ra9tbwVrePpB0iHQQR6Rnwh90Bfna57ynnzN5x/EAcTxDAX73IpcbDHTx2tsSXbhff/HXIVQILSOjEaXdTXbp3LmmSS1u9N+kTEgVnlKr5thjVZD2guCB3u8fZDa1kqZKrOWrcDWPffRfUl8rrHsVU6pr4MpBqxw8riVqyrwcci5eS7TYDsWObM+/lLNm6VjwiUlUSg+PSCrjzUK4b6o4jJEcWYSdfA1ZHWBoEK1bRwwKCAQEA3scx12TImHUxi8YkDOx/frE/9r+iQWzQJ0w6FB/G/wmF2SWLDvFrb2hIECNB5s8tYn24Okqln0ql6LGXCMjSEUwfPHjPFDUuibCM43dOxCqNdUhIKFjR6FCzzIfxH1r0HskZmsMkBCayvGYtVrTF3I9ONM7osufjDpJgIjmfBvomTgWIPF5A6w6JTslrj8u1JKlByjbupfrm91r/uBrwZFNffMpbdR5vi3DT9q8Pu5TxBWk6zHV0XE1H/XfAmHVr91nUeVc9KGq2kdVsG2AbSY+eyFCQouYgUBj0PVvsyWlAp0LzqiCxt77pNo91oJBOsM5NLkEUDb19e3y0C021gQKCAQAngQXMFj6bspgHglzZv25e/s/hp/0rshJ0FmqBx5UzlOBy+ylnh2sgjQ2G1vHah/8NqbZh3E27X1J/buEXMhBoDzD6ULIwtXpl7ifylp000M1/Tq0LCtokekjh1vIFXoVwPz4bL3mllK3eh8etj5Cm7oq+FpBwFl2vcIbbuO+5kiPotTeij7HMRzCDyzlKtR9lKIOL5OS++uhMFTqxoZpB8ei5gK+ruC7UIUTSw+8ZWqy08fx7aryoqE65dOA+1k+As/CoPveqmByIOm9U05ZqDgs8KwobDCB0O+STkbRCVOhtCMVUDAuNdek4Hhd95ZaLA5wXX8ybLLQOgRvrbx1JAoIBAQCHhfUfewhlhQLHIfoSd7Q6Flvey5yIHJUTfRsvAcm5la5hNwL9prfPvjipvxwdxQmocuwTnv3s3XpwWY8t/EG8Sk0cmOdVCoh56ns/P/Xxx5GfMoEBRQNl8E1evgR6hJ6ZbvcCHEhyxF1Pr9VyB24UWA5gePbMuSh88ptA+KpfUzFjwV7HBriBGMax9wXrod9zoJ61gYuU8rXzDjYtYjPWUWJusFFwWSuKZKL5r1QXmAz6prNaepJ4K0GqnQe9oEGnCZai2SFFr2yd2I9mqof3OtN/cfp2utYw/1E1apbsMpjoc1YMOTud10sHgMXtPA2SpO+fn6Mg/OSy+UW4rNOp").unwrap();
         let private_key = RsaPrivateKey::from_pkcs1_der(&private_key_der).unwrap();
         let user = User {
+            ed25519_private_key: None,
+            ed25519_public_key: None,
+            hide_followers: false,
+            manually_approves_followers: false,
             preferred_username: Some(username.to_owned()),
+            previous_ed25519_public_key: None,
+            previous_public_key: None,
             private_key: Some(private_key_der),
             public_key: Some(
                 private_key
@@ -359,10 +441,12 @@ mod tests {
                     .to_vec(),
             ),
             published_unix_time_seconds: 0,
+            rotated_unix_time_seconds: None,
         };
         let signature_key_id = "https://example.com/users/test_username#main-key";
         let follower = Object {
             actor: Some(format!("https://{domain}/users/follower")),
+            assertion_method: None,
             atom_uri: None,
             attachment: Some(Vec::new()),
             attributed_to: None,
@@ -372,12 +456,13 @@ mod tests {
             conversation: None,
             devices: None,
             discoverable: Some(false),
+            endpoints: None,
             followers: None,
             following: None,
             id: Some(format!("https://{domain}/users/follower")),
             in_reply_to: None,
             in_reply_to_atom_uri: None,
-            inbox: None,
+            inbox: Some(format!("https://{domain}/users/follower/inbox")),
             manually_approves_followers: None,
             name: None,
             object: None,
@@ -396,7 +481,7 @@ mod tests {
             extra: serde_json::Value::Null,
         };
         let expected_url = "https://example.com/users/follower/inbox";
-        let expected_request_body = "{\"attachment\":[],\"content\":\"test content\",\"@context\":[\"https://www.w3.org/ns/activitystreams\",\"https://w3id.org/security/v1\",{\"Curve25519Key\":\"toot:Curve25519Key\",\"Device\":\"toot:Device\",\"Ed25519Key\":\"toot:Ed25519Key\",\"Ed25519Signature\":\"toot:Ed25519Signature\",\"EncryptedMessage\":\"toot:EncryptedMessage\",\"PropertyValue\":\"schema:PropertyValue\",\"alsoKnownAs\":{\"@id\":\"as:alsoKnownAs\",\"@type\":\"@id\"},\"cipherText\":\"toot:cipherText\",\"claim\":{\"@id\":\"toot:claim\",\"@type\":\"@id\"},\"deviceId\":\"toot:deviceId\",\"devices\":{\"@id\":\"toot:devices\",\"@type\":\"@id\"},\"discoverable\":\"toot:discoverable\",\"featured\":{\"@id\":\"toot:featured\",\"@type\":\"@id\"},\"featuredTags\":{\"@id\":\"toot:featuredTags\",\"@type\":\"@id\"},\"fingerprintKey\":{\"@id\":\"toot:fingerprintKey\",\"@type\":\"@id\"},\"identityKey\":{\"@id\":\"toot:identityKey\",\"@type\":\"@id\"},\"manuallyApprovesFollowers\":\"as:manuallyApprovesFollowers\",\"messageFranking\":\"toot:messageFranking\",\"messageType\":\"toot:messageType\",\"movedTo\":{\"@id\":\"as:movedTo\",\"@type\":\"@id\"},\"publicKeyBase64\":\"toot:publicKeyBase64\",\"schema\":\"http://schema.org#\",\"suspended\":\"toot:suspended\",\"toot\":\"http://joinmastodon.org/ns#\",\"value\":\"schema:value\"}],\"conversation\":\"tag:example.com,2019-04-28:objectId=1754000:objectType=Conversation\",\"discoverable\":false,\"id\":\"https://example.com/users/test_username/statuses/1234567890\",\"published\":\"2023-01-19T00:00:00Z\",\"type\":\"Note\",\"sensitive\":false,\"tag\":[],\"to\":[\"https://www.w3.org/ns/activitystreams#Public\"],\"url\":\"https://example.com/@test_username\"}";
+        let expected_request_body = "{\"@context\":\"https://www.w3.org/ns/activitystreams\",\"actor\":\"https://example.com/users/test_username\",\"cc\":[\"https://example.com/users/test_username/followers\"],\"id\":\"https://example.com/users/test_username/statuses/1234567890/activity\",\"object\":{\"@context\":[\"https://www.w3.org/ns/activitystreams\",\"https://w3id.org/security/v1\",{\"Curve25519Key\":\"toot:Curve25519Key\",\"Device\":\"toot:Device\",\"Ed25519Key\":\"toot:Ed25519Key\",\"Ed25519Signature\":\"toot:Ed25519Signature\",\"EncryptedMessage\":\"toot:EncryptedMessage\",\"PropertyValue\":\"schema:PropertyValue\",\"alsoKnownAs\":{\"@id\":\"as:alsoKnownAs\",\"@type\":\"@id\"},\"cipherText\":\"toot:cipherText\",\"claim\":{\"@id\":\"toot:claim\",\"@type\":\"@id\"},\"deviceId\":\"toot:deviceId\",\"devices\":{\"@id\":\"toot:devices\",\"@type\":\"@id\"},\"discoverable\":\"toot:discoverable\",\"featured\":{\"@id\":\"toot:featured\",\"@type\":\"@id\"},\"featuredTags\":{\"@id\":\"toot:featuredTags\",\"@type\":\"@id\"},\"fingerprintKey\":{\"@id\":\"toot:fingerprintKey\",\"@type\":\"@id\"},\"identityKey\":{\"@id\":\"toot:identityKey\",\"@type\":\"@id\"},\"manuallyApprovesFollowers\":\"as:manuallyApprovesFollowers\",\"messageFranking\":\"toot:messageFranking\",\"messageType\":\"toot:messageType\",\"movedTo\":{\"@id\":\"as:movedTo\",\"@type\":\"@id\"},\"publicKeyBase64\":\"toot:publicKeyBase64\",\"schema\":\"http://schema.org#\",\"suspended\":\"toot:suspended\",\"toot\":\"http://joinmastodon.org/ns#\",\"value\":\"schema:value\"}],\"actor\":\"https://example.com/users/test_username\",\"attachment\":[],\"cc\":[\"https://example.com/users/test_username/followers\"],\"content\":\"test content\",\"conversation\":\"tag:example.com,2019-04-28:objectId=1754000:objectType=Conversation\",\"discoverable\":false,\"id\":\"https://example.com/users/test_username/statuses/1234567890\",\"published\":\"2023-01-19T00:00:00Z\",\"sensitive\":false,\"tag\":[],\"to\":[\"https://www.w3.org/ns/activitystreams#Public\"],\"type\":\"Note\",\"url\":\"https://example.com/@test_username\"},\"published\":\"2023-01-19T00:00:00Z\",\"to\":[\"https://www.w3.org/ns/activitystreams#Public\"],\"type\":\"Create\"}";
         let mut expected_headers = HeaderMap::new();
         expected_headers.append(
             "content-type",
@@ -405,7 +490,7 @@ mod tests {
         expected_headers.append("host", HeaderValue::from_str("example.com").unwrap());
         expected_headers.append(
             "digest",
-            HeaderValue::from_str("SHA-256=cDjf+fYECF0XKY1THmYVgFNRzP5s2M1lx7TGQkWvbxI=").unwrap(),
+            HeaderValue::from_str("SHA-256=5gua2iDE/yuOwNN5A8o6HkoKq3gd1jR4VRphvIe16YQ=").unwrap(),
         );
         expected_headers.append(
             "date",
@@ -413,16 +498,197 @@ mod tests {
         );
         expected_headers.append(
             "signature",
-            HeaderValue::from_str("keyId=\"https://example.com/users/test_username#main-key\",algorithm=\"rsa-sha256\",headers=\"(request-target) host date digest content-type\",signature=\"QPNXXvmfOx0jHzRoUCZywql2KdoXgHrdJvkS/tzwD8CIy161GPTCXTnk9daKy3Q1Us6GtbGEJ/ho1rAI0LwDzp2iOLeozvw0mrMYlF4hP/xHLLzQtq2mbYpM9ZvtWRFUOCf0YTcBpBnW9pS3Rqqw+vTKCrMpDggmryvn8Dnn5OQ6j9Q+AiuOABXpGZbmws2jdPxRkWde6j+Otia7fACQyjsetrvFisdoFWBWHZdl5bsSOTZIm9wm3zQOpMHO4xMQ4q6vgVHNStESBDism7Yba/ikqs7hVWmf1Gg4vs+++3BKkQvNVHIrRBJsXmXQ+YsYV+Cxwn+BRTWnoxGQR5P/G6ER4oSeiNHIexchpelrfbi6TL62uQsFL6L5QYALJcUE+aFHgXaYzCTsVteCCrG58+wHAPd5arOZRaAF0GiZQKvZJY/r4eYvkk7hew1ZOpydFysNPIdcbMz18qBtEUMlbDIiez4dHp5VqyJRktKAnUjpWFh7yXBHEC8062JdICWuV7wzpJeEvHRRE718bkleyUcJ96RuGGvfmBj0RsbyNOtQpZZu1WmPvBRZqiQQ40lBI5So+2ao6cUZNMxLch9Saul9YYQkpSXbHiCxmQZrAjWCtN5uWakFF4o+KZL6cZ6OBxkTZhxuFtyiO9ayHlfIyTRZ+pjaefVnSTtooJujQhU=\"").unwrap());
+            HeaderValue::from_str("keyId=\"https://example.com/users/test_username#main-key\",algorithm=\"rsa-sha256\",headers=\"(request-target) host date digest content-type\",signature=\"XRbtJ6kNQQQBTFs9dZwU2UKGh6HIERZz1ufLWMtZwWPqrLUOhlBknRaP7dykiN2TR11F1uK8dVTT9u4jtE+MnYEe3NHd1Y0R4Jj1y4QRRx5V2/3kzVxu9TkU3zPTO4Oi8do6pc7i2jaUV2AE8VM18n2Ct0383SSdIKtp42zlmExue0Ne3b90tTGyvuYlMwGjkoZrCh+KbDNbDWCchk2/1rpF+2k+fd4YXNFacxt+oM1BkcyfzQ/Co8kYrjo+e+RN3kG5PuTIHL4vLoxcpBNM7nkSrimCjl/OC5vkt+8ZNZGok5/5edJV/T4D3ZQnVWmrfeOjkEYJzgudE6Bbo6MxgCFKrJKu7BLUJLLNl6nAzKV4gkyulGKlayWAfvBuW0QT8Sd3C98wLZ+T+ZLYQDnUfzYNyIJMTYjGQoxZwzegYidES93BlfGc5bvzaSJL4rsoLtmu0fbp1xHE1uRUItXMNS4CzLGp5iZz7EOLND7DZWiOX7prTrwqLELdYyQcQLwlHMZy+Ixfgaz60W0Y+sW5ydiqpJCAkZlCDoK4Fbtm5wQVVwMS/x+09riLbRt/rJ+d1GUz4xeecYscouJXNqDd/2/d8SVcfU4zWqQ+6pCqJpxHpiDAHL5upkqqJ8zskdh0dY+K8NtwKM8shpoxIa12enkmTsvmL6Yj4zp9n2dSAwI=\"").unwrap());
         let time_provider = MockTimeProvider {};
 
         // Act
-        let (actual_url, actual_request_body, actual_headers) =
-            get_notification(&status, &user, signature_key_id, &follower, &time_provider);
+        let store = MemoryStore::new();
+        library::store::put(
+            &store,
+            "actor/example.com",
+            "https://example.com/users/follower",
+            &follower,
+        )
+        .await
+        .unwrap();
+        let inboxes = get_inboxes(&[follower.id.clone().unwrap()], &store).await;
+        let activity = get_activity(&DynamoDBOperationType::Insert, &status, 0).unwrap();
+        let delivery = get_fan_out(&activity, username, signature_key_id)
+            .delivery(inboxes.keys().next().unwrap(), 0);
+        let actual_headers = get_headers(&delivery, &user, &time_provider).unwrap();
 
         // Assert
-        assert_eq!(expected_url, actual_url);
-        assert_eq!(expected_request_body, actual_request_body);
+        assert_eq!(expected_url, delivery.inbox);
+        assert_eq!(expected_request_body, delivery.body);
         assert_eq!(expected_headers, actual_headers);
     }
+
+    fn record(sequence_number: &str, new_image: &Value) -> DynamoDBEventRecord {
+        serde_json::from_value(json!({
+            "awsRegion": "us-east-1",
+            "dynamodb": {
+                "ApproximateCreationDateTime": 1674086400.0,
+                "NewImage": new_image,
+                "SequenceNumber": sequence_number,
+                "SizeBytes": 100,
+                "StreamViewType": "NEW_AND_OLD_IMAGES"
+            },
+            "eventID": sequence_number,
+            "eventName": "INSERT",
+            "eventSource": "aws:dynamodb",
+            "eventVersion": "1.1",
+            "eventSourceARN": "arn:aws:dynamodb:us-east-1:123456789012:table/t/stream/1"
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_process_records_partial_failure() {
+        // Arrange
+        let store = MemoryStore::new();
+        let status = json!({
+            "partition_key": { "S": "users/a/statuses" },
+            "sort_key": { "S": "1" },
+            "id": { "S": "https://example.com/users/a/statuses/1" },
+            "type": { "S": "Note" },
+            "in_reply_to": { "NULL": true }
+        });
+        let empty_value = json!({
+            "partition_key": { "S": "users/a/statuses" },
+            "content": {}
+        });
+        let records = vec![
+            record("100", &status),
+            record("200", &empty_value),
+            record("300", &json!({ "sort_key": { "S": "2" } })),
+        ];
+
+        // Act
+        let actual = process_records(records, 0, &store, "example.com").await;
+
+        // Assert
+        assert_eq!(actual, ["200", "300"]);
+        let fan_outs = delivery::get_fan_outs(&store, 10).await.unwrap();
+        assert_eq!(
+            fan_outs[0].activity_id,
+            "https://example.com/users/a/statuses/1/activity"
+        );
+        assert_eq!(
+            get_response(&actual),
+            json!({
+                "batchItemFailures": [
+                    { "itemIdentifier": "200" },
+                    { "itemIdentifier": "300" }
+                ]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_delivery_records() {
+        // Arrange
+        std::env::set_var("CUSTOM_DOMAIN", "example.com");
+        let store = MemoryStore::new();
+        let fan_out = FanOut {
+            username: "a".to_owned(),
+            activity_id: "https://example.com/users/a/statuses/1/activity".to_owned(),
+            body: "{}".to_owned(),
+            signature_key_id: "https://example.com/users/a#main-key".to_owned(),
+            cursor: None,
+        };
+        delivery::put_fan_out(&store, &fan_out).await.unwrap();
+        let records = json!({
+            "Records": [
+                record("100", &json!({ "partition_key": { "S": "deliveries" } })),
+                record("200", &json!({ "partition_key": { "S": "users/a/deliveries" } })),
+            ]
+        });
+
+        // Act
+        let actual = handle(records, &store, u64::MAX).await.unwrap();
+
+        // Assert
+        assert_eq!(actual, get_response(&[]));
+        assert_eq!(delivery::get_fan_outs(&store, 10).await.unwrap(), [fan_out]);
+    }
+
+    #[tokio::test]
+    async fn test_get_inboxes_shared() {
+        // Arrange
+        let store = MemoryStore::new();
+        for username in ["a", "b"] {
+            let actor_id = format!("https://remote.example/users/{username}");
+            let actor = serde_json::json!({
+                "id": actor_id,
+                "inbox": format!("{actor_id}/inbox"),
+                "endpoints": { "sharedInbox": "https://remote.example/inbox" },
+            });
+            library::store::put(&store, "actor/remote.example", &actor_id, &actor)
+                .await
+                .unwrap();
+        }
+        let actor_ids = [
+            "https://remote.example/users/a".to_owned(),
+            "https://remote.example/users/b".to_owned(),
+        ];
+
+        // Act
+        let actual = get_inboxes(&actor_ids, &store).await;
+
+        // Assert
+        assert_eq!(
+            actual,
+            BTreeMap::from([("https://remote.example/inbox".to_owned(), false)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_inboxes_ed25519() {
+        // Arrange
+        let store = MemoryStore::new();
+        let actor_id = "https://remote.example/users/a";
+        let actor = serde_json::json!({
+            "id": actor_id,
+            "inbox": format!("{actor_id}/inbox"),
+            "assertionMethod": {
+                "id": format!("{actor_id}#ed25519-key"),
+                "type": "Multikey",
+                "controller": actor_id,
+                "publicKeyMultibase": "z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2",
+            },
+        });
+        library::store::put(&store, "actor/remote.example", actor_id, &actor)
+            .await
+            .unwrap();
+
+        // Act
+        let actual = get_inboxes(&[actor_id.to_owned()], &store).await;
+
+        // Assert
+        assert_eq!(
+            actual,
+            BTreeMap::from([(format!("{actor_id}/inbox"), true)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_inboxes_missing() {
+        // Arrange
+        let store = MemoryStore::new();
+        let actor = serde_json::json!({ "id": "https://remote.example/users/a" });
+        library::store::put(
+            &store,
+            "actor/remote.example",
+            "https://remote.example/users/a",
+            &actor,
+        )
+        .await
+        .unwrap();
+
+        // Act
+        let actual = get_inboxes(&["https://remote.example/users/a".to_owned()], &store).await;
+
+        // Assert
+        assert!(actual.is_empty());
+    }
 }
@@ -0,0 +1,25 @@
+use library::settings::Settings;
+use library::store::DynamoDbStore;
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 5 {
+        panic!("Usage: follow username domain dynamodb_table_name user@remote.domain")
+    }
+    let domain = args[2].clone();
+    let settings = Settings {
+        base_url: format!("https://{domain}"),
+        domain_name: domain,
+        node_id: library::faas_snowflake_id::get_node_id(),
+        store: Box::new(DynamoDbStore::new(
+            library::dynamodb::get_client().await,
+            args[3].clone(),
+        )),
+    };
+    let settings = <&rocket::State<Settings>>::from(&settings);
+    let actor_id = library::model::following::follow(&args[1], &args[4], settings)
+        .await
+        .unwrap();
+    println!("{actor_id}");
+}
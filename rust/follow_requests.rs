@@ -0,0 +1,39 @@
+use library::model::{follow_request, user};
+use library::settings::Settings;
+use library::store::DynamoDbStore;
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 5 {
+        panic!("Usage: follow_requests username domain dynamodb_table_name lock|unlock|hide_followers|show_followers|list|approve actor_id|reject actor_id")
+    }
+    let username = args[1].as_str();
+    let domain = args[2].clone();
+    let settings = Settings {
+        base_url: format!("https://{domain}"),
+        domain_name: domain,
+        node_id: library::faas_snowflake_id::get_node_id(),
+        store: Box::new(DynamoDbStore::new(
+            library::dynamodb::get_client().await,
+            args[3].clone(),
+        )),
+    };
+    let settings = <&rocket::State<Settings>>::from(&settings);
+    let result = match (args[4].as_str(), args.get(5)) {
+        ("lock", None) => user::set_manually_approves_followers(username, true, settings).await,
+        ("unlock", None) => user::set_manually_approves_followers(username, false, settings).await,
+        ("hide_followers", None) => user::set_hide_followers(username, true, settings).await,
+        ("show_followers", None) => user::set_hide_followers(username, false, settings).await,
+        ("list", None) => follow_request::list(username, settings)
+            .await
+            .map(|follows| follows.iter().for_each(|follow| println!("{follow}"))),
+        ("approve", Some(actor_id)) => follow_request::approve(username, actor_id, settings).await,
+        ("reject", Some(actor_id)) => follow_request::reject(username, actor_id, settings).await,
+        _ => Err(library::Error::Invalid(format!(
+            "Unknown command {}",
+            args[4..].join(" ")
+        ))),
+    };
+    result.unwrap();
+}
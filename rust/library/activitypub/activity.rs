@@ -0,0 +1,392 @@
+use super::object::Object;
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Clone, Copy, Deserialize, PartialEq, Eq, Debug)]
+pub enum ActivityType {
+    Accept,
+    Announce,
+    Block,
+    Create,
+    Delete,
+    Follow,
+    Like,
+    Move,
+    Reject,
+    Undo,
+    Update,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Clone, Deserialize, PartialEq, Eq, Debug)]
+pub struct Activity {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub r#type: ActivityType,
+    pub actor: String,
+    #[serde(default)]
+    pub object: Value,
+    #[serde(default)]
+    pub target: Option<String>,
+}
+
+impl Activity {
+    /// # Errors
+    ///
+    /// Will return `Err` if the value lacks a `type` or an `actor`.
+    pub fn from_value(value: &Value) -> crate::Result<Self> {
+        serde_json::from_value(value.clone())
+            .map_err(|e| crate::Error::Parse(format!("invalid activity: {e}")))
+    }
+
+    /// The id of the object, whether it was sent as a bare IRI or embedded.
+    #[must_use]
+    pub fn object_id(&self) -> Option<&str> {
+        self.object.as_str().or_else(|| self.object["id"].as_str())
+    }
+
+    /// The type of the object, only known when the object is embedded.
+    #[must_use]
+    pub fn object_type(&self) -> Option<&str> {
+        self.object["type"].as_str()
+    }
+}
+
+/// Builds a `Follow` of `object` by `actor`.
+#[must_use]
+pub fn follow(id: &str, actor: &str, object: &str) -> Value {
+    serde_json::json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": id,
+        "type": "Follow",
+        "actor": actor,
+        "object": object,
+    })
+}
+
+/// Builds the `Create` of a status, the same whether it's delivered or served by the outbox.
+///
+/// # Errors
+///
+/// Will return `Err` if the status has no id.
+pub fn create(status: &Object) -> crate::Result<Value> {
+    let id = status_id(status)?;
+    let mut activity = on_status("Create", &format!("{id}/activity"), status, &json(status)?);
+    activity["published"] = json(&status.published)?;
+    Ok(activity)
+}
+
+/// Builds the `Update` of an edited status.
+///
+/// Each edit gets its own id from the time it was made, so that it's delivered again.
+///
+/// # Errors
+///
+/// Will return `Err` if the status has no id.
+pub fn update(status: &Object, updated_unix_time_seconds: u64) -> crate::Result<Value> {
+    let id = status_id(status)?;
+    Ok(on_status(
+        "Update",
+        &format!("{id}#updates/{updated_unix_time_seconds}"),
+        status,
+        &json(status)?,
+    ))
+}
+
+/// Builds the `Delete` of a status, which leaves a `Tombstone` in its place.
+///
+/// # Errors
+///
+/// Will return `Err` if the status has no id.
+pub fn delete(status: &Object) -> crate::Result<Value> {
+    let id = status_id(status)?;
+    let tombstone = serde_json::json!({
+        "id": id,
+        "type": "Tombstone",
+    });
+    Ok(on_status(
+        "Delete",
+        &format!("{id}#delete"),
+        status,
+        &tombstone,
+    ))
+}
+
+/// Builds the `Update` of an actor, sent to its followers so that they fetch its new keys.
+///
+/// # Errors
+///
+/// Will return `Err` if the actor has no id.
+pub fn update_actor(actor: &Object, updated_unix_time_seconds: u64) -> crate::Result<Value> {
+    let id = actor
+        .id
+        .as_deref()
+        .ok_or_else(|| crate::Error::Parse("actor without id".to_owned()))?;
+    Ok(serde_json::json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": format!("{id}#updates/{updated_unix_time_seconds}"),
+        "type": "Update",
+        "actor": id,
+        "to": ["https://www.w3.org/ns/activitystreams#Public"],
+        "cc": actor.followers.iter().collect::<Vec<_>>(),
+        "object": json(actor)?,
+    }))
+}
+
+fn status_id(status: &Object) -> crate::Result<&str> {
+    status
+        .id
+        .as_deref()
+        .ok_or_else(|| crate::Error::Parse("status without id".to_owned()))
+}
+
+fn json<T: serde::Serialize>(value: &T) -> crate::Result<Value> {
+    Ok(serde_json::to_value(value)?)
+}
+
+/// Addressed to the same audience as the status.
+fn on_status(r#type: &str, id: &str, status: &Object, object: &Value) -> Value {
+    serde_json::json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": id,
+        "type": r#type,
+        "actor": status.actor,
+        "to": status.to,
+        "cc": status.cc,
+        "object": object,
+    })
+}
+
+/// Builds an `Accept` of `follow` on behalf of the followed `actor`.
+#[must_use]
+pub fn accept(id: &str, actor: &str, follow: &Value) -> Value {
+    response("Accept", id, actor, follow)
+}
+
+/// Builds a `Reject` of `follow` on behalf of the followed `actor`.
+#[must_use]
+pub fn reject(id: &str, actor: &str, follow: &Value) -> Value {
+    response("Reject", id, actor, follow)
+}
+
+fn response(r#type: &str, id: &str, actor: &str, follow: &Value) -> Value {
+    serde_json::json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": id,
+        "type": r#type,
+        "actor": actor,
+        "object": follow,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_value() {
+        // Arrange
+        let value = serde_json::json!({
+            "@context": "https://www.w3.org/ns/activitystreams",
+            "id": "https://example.com/a568596b",
+            "type": "Follow",
+            "actor": "https://example.com/users/follower",
+            "object": "https://example.org/users/followed"
+        });
+
+        // Act
+        let actual = Activity::from_value(&value).unwrap();
+
+        // Assert
+        assert_eq!(actual.r#type, ActivityType::Follow);
+        assert_eq!(actual.actor, "https://example.com/users/follower");
+        assert_eq!(
+            actual.object_id(),
+            Some("https://example.org/users/followed")
+        );
+        assert_eq!(actual.object_type(), None);
+    }
+
+    #[test]
+    fn test_from_value_embedded_object() {
+        // Arrange
+        let value = serde_json::json!({
+            "type": "Undo",
+            "actor": "https://example.com/users/follower",
+            "object": {
+                "id": "https://example.com/a568596b",
+                "type": "Follow",
+                "actor": "https://example.com/users/follower",
+                "object": "https://example.org/users/followed"
+            }
+        });
+
+        // Act
+        let actual = Activity::from_value(&value).unwrap();
+
+        // Assert
+        assert_eq!(actual.r#type, ActivityType::Undo);
+        assert_eq!(actual.object_id(), Some("https://example.com/a568596b"));
+        assert_eq!(actual.object_type(), Some("Follow"));
+    }
+
+    #[test]
+    fn test_from_value_unknown_type() {
+        // Arrange
+        let value = serde_json::json!({
+            "type": "EmojiReact",
+            "actor": "https://example.com/users/follower",
+        });
+
+        // Act
+        let actual = Activity::from_value(&value).unwrap();
+
+        // Assert
+        assert_eq!(actual.r#type, ActivityType::Unknown);
+    }
+
+    #[test]
+    fn test_follow() {
+        // Arrange
+        let expected = serde_json::json!({
+            "@context": "https://www.w3.org/ns/activitystreams",
+            "id": "https://example.org/users/follower#follows/1",
+            "type": "Follow",
+            "actor": "https://example.org/users/follower",
+            "object": "https://example.com/users/followed"
+        });
+
+        // Act
+        let actual = follow(
+            "https://example.org/users/follower#follows/1",
+            "https://example.org/users/follower",
+            "https://example.com/users/followed",
+        );
+
+        // Assert
+        assert_eq!(expected, actual);
+        assert_eq!(
+            Activity::from_value(&actual).unwrap().r#type,
+            ActivityType::Follow
+        );
+    }
+
+    #[test]
+    fn test_accept() {
+        // Arrange
+        let follow = serde_json::json!({
+            "id": "https://example.com/a568596b",
+            "type": "Follow",
+            "actor": "https://example.com/users/follower",
+            "object": "https://example.org/users/followed"
+        });
+        let expected = serde_json::json!({
+            "@context": "https://www.w3.org/ns/activitystreams",
+            "id": "https://example.org/users/followed#accepts/follows/1",
+            "type": "Accept",
+            "actor": "https://example.org/users/followed",
+            "object": {
+                "id": "https://example.com/a568596b",
+                "type": "Follow",
+                "actor": "https://example.com/users/follower",
+                "object": "https://example.org/users/followed"
+            }
+        });
+
+        // Act
+        let actual = accept(
+            "https://example.org/users/followed#accepts/follows/1",
+            "https://example.org/users/followed",
+            &follow,
+        );
+
+        // Assert
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_create() {
+        // Arrange
+        let status: Object = serde_json::from_value(serde_json::json!({
+            "id": "https://example.com/users/a/statuses/1",
+            "type": "Note",
+            "actor": "https://example.com/users/a",
+            "published": "2023-01-19T00:00:00Z",
+            "to": ["https://www.w3.org/ns/activitystreams#Public"],
+            "cc": ["https://example.com/users/a/followers"],
+            "content": "test content"
+        }))
+        .unwrap();
+
+        // Act
+        let actual = create(&status).unwrap();
+
+        // Assert
+        assert_eq!(
+            actual["id"],
+            "https://example.com/users/a/statuses/1/activity"
+        );
+        assert_eq!(actual["type"], "Create");
+        assert_eq!(actual["actor"], "https://example.com/users/a");
+        assert_eq!(actual["cc"][0], "https://example.com/users/a/followers");
+        assert_eq!(actual["object"]["content"], "test content");
+        assert_eq!(
+            Activity::from_value(&actual).unwrap().object_id(),
+            Some("https://example.com/users/a/statuses/1")
+        );
+    }
+
+    #[test]
+    fn test_update_and_delete() {
+        // Arrange
+        let status: Object = serde_json::from_value(serde_json::json!({
+            "id": "https://example.com/users/a/statuses/1",
+            "type": "Note",
+            "actor": "https://example.com/users/a",
+            "to": ["https://www.w3.org/ns/activitystreams#Public"],
+            "content": "edited content"
+        }))
+        .unwrap();
+
+        // Act
+        let updated = update(&status, 1_674_086_400).unwrap();
+        let deleted = delete(&status).unwrap();
+
+        // Assert
+        assert_eq!(
+            updated["id"],
+            "https://example.com/users/a/statuses/1#updates/1674086400"
+        );
+        assert_eq!(updated["type"], "Update");
+        assert_eq!(updated["object"]["content"], "edited content");
+        assert_eq!(
+            deleted["id"],
+            "https://example.com/users/a/statuses/1#delete"
+        );
+        assert_eq!(deleted["type"], "Delete");
+        assert_eq!(
+            deleted["to"][0],
+            "https://www.w3.org/ns/activitystreams#Public"
+        );
+        assert_eq!(
+            deleted["object"],
+            serde_json::json!({
+                "id": "https://example.com/users/a/statuses/1",
+                "type": "Tombstone"
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_value_missing_actor() {
+        // Arrange
+        let value = serde_json::json!({ "type": "Follow" });
+
+        // Act
+        let actual = Activity::from_value(&value);
+
+        // Assert
+        assert!(actual.is_err());
+    }
+}
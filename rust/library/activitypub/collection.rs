@@ -0,0 +1,147 @@
+use rocket::http::RawStr;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Position of a collection page, relative to the sort key of an item.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Cursor<'a> {
+    First,
+    After(&'a str),
+    Before(&'a str),
+}
+
+impl<'a> Cursor<'a> {
+    #[must_use]
+    pub const fn new(after: Option<&'a str>, before: Option<&'a str>) -> Self {
+        match (after, before) {
+            (Some(after), _) => Self::After(after),
+            (None, Some(before)) => Self::Before(before),
+            (None, None) => Self::First,
+        }
+    }
+}
+
+/// Items of a collection page, with the sort keys to continue from in each direction.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+#[must_use]
+pub fn page_url(collection_id: &str, cursor: Cursor<'_>) -> String {
+    match cursor {
+        Cursor::First => format!("{collection_id}?page=true"),
+        Cursor::After(after) => format!(
+            "{collection_id}?page=true&after={}",
+            RawStr::new(after).percent_encode()
+        ),
+        Cursor::Before(before) => format!(
+            "{collection_id}?page=true&before={}",
+            RawStr::new(before).percent_encode()
+        ),
+    }
+}
+
+/// An `OrderedCollection` that links to its first page, unless its items are hidden.
+#[must_use]
+pub fn ordered_collection(id: &str, total_items: i32, hide_items: bool) -> Value {
+    let mut collection = serde_json::json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": id,
+        "type": "OrderedCollection",
+        "totalItems": total_items,
+    });
+    if !hide_items {
+        collection["first"] = Value::String(page_url(id, Cursor::First));
+    }
+    collection
+}
+
+#[must_use]
+pub fn ordered_collection_page<T: Serialize>(
+    collection_id: &str,
+    cursor: Cursor<'_>,
+    page: Page<T>,
+) -> Value {
+    let mut collection_page = serde_json::json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": page_url(collection_id, cursor),
+        "type": "OrderedCollectionPage",
+        "partOf": collection_id,
+        "orderedItems": page.items,
+    });
+    if let Some(next) = page.next {
+        collection_page["next"] = Value::String(page_url(collection_id, Cursor::After(&next)));
+    }
+    if let Some(prev) = page.prev {
+        collection_page["prev"] = Value::String(page_url(collection_id, Cursor::Before(&prev)));
+    }
+    collection_page
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_url() {
+        assert_eq!(
+            page_url("https://example.com/users/user/followers", Cursor::First),
+            "https://example.com/users/user/followers?page=true"
+        );
+        assert_eq!(
+            page_url(
+                "https://example.com/users/user/followers",
+                Cursor::After("https://example.org/users/follower")
+            ),
+            "https://example.com/users/user/followers?page=true&after=https:%2F%2Fexample.org%2Fusers%2Ffollower"
+        );
+    }
+
+    #[test]
+    fn test_ordered_collection_hide_items() {
+        // Arrange
+        let expected = serde_json::json!({
+            "@context": "https://www.w3.org/ns/activitystreams",
+            "id": "https://example.com/users/user/followers",
+            "type": "OrderedCollection",
+            "totalItems": 3,
+        });
+
+        // Act
+        let actual = ordered_collection("https://example.com/users/user/followers", 3, true);
+
+        // Assert
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_ordered_collection_page() {
+        // Arrange
+        let page = Page {
+            items: vec!["https://example.org/users/b"],
+            next: Some("https://example.org/users/b".to_owned()),
+            prev: None,
+        };
+        let expected = serde_json::json!({
+            "@context": "https://www.w3.org/ns/activitystreams",
+            "id": "https://example.com/users/user/followers?page=true",
+            "type": "OrderedCollectionPage",
+            "partOf": "https://example.com/users/user/followers",
+            "orderedItems": ["https://example.org/users/b"],
+            "next": "https://example.com/users/user/followers?page=true&after=https:%2F%2Fexample.org%2Fusers%2Fb",
+        });
+
+        // Act
+        let actual = ordered_collection_page(
+            "https://example.com/users/user/followers",
+            Cursor::First,
+            page,
+        );
+
+        // Assert
+        assert_eq!(expected, actual);
+    }
+}
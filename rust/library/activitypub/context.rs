@@ -77,3 +77,13 @@ pub fn default() -> Option<serde_json::Value> {
     )
     .ok()
 }
+
+/// The context of actors, which also defines the FEP-521a Multikeys of their `assertionMethod`.
+#[must_use]
+pub fn actor() -> Option<serde_json::Value> {
+    let mut context = default()?;
+    context
+        .as_array_mut()?
+        .insert(2, "https://w3id.org/security/multikey/v1".into());
+    Some(context)
+}
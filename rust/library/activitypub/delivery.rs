@@ -0,0 +1,71 @@
+use http::header::{HeaderMap, HeaderValue};
+use tracing::{event, Level};
+
+const METHOD: &str = "POST";
+
+/// Builds the signed headers needed to POST `request_body` to `url`.
+///
+/// # Errors
+///
+/// Will return `Err` if the request can´t be signed.
+pub fn get_headers(
+    url: &str,
+    request_body: &str,
+    private_key: &[u8],
+    signature_key_id: &str,
+    time_provider: &dyn crate::time_provider::TimeProvider,
+) -> crate::Result<HeaderMap> {
+    let split_url = url.splitn(4, '/').collect::<Vec<&str>>();
+    let host = split_url.get(2).copied().unwrap_or_default();
+    let path = format!("/{}", split_url.get(3).copied().unwrap_or_default());
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "Content-Type",
+        HeaderValue::from_static("application/activity+json"),
+    );
+    headers.insert(
+        "Host",
+        HeaderValue::from_str(host)
+            .map_err(|e| crate::Error::Parse(format!("Invalid host in {url}: {e:?}")))?,
+    );
+    super::request::sign(
+        METHOD,
+        path.as_str(),
+        &mut headers,
+        request_body,
+        private_key,
+        signature_key_id,
+        time_provider,
+    )?;
+    Ok(headers)
+}
+
+/// # Errors
+///
+/// Will return `Err` if the request fails or the inbox doesn't answer with a success status,
+/// [`crate::Error::Unavailable`] when it's worth trying again later.
+pub async fn post(
+    http_client: &reqwest::Client,
+    url: &str,
+    request_body: String,
+    headers: HeaderMap,
+) -> crate::Result<()> {
+    event!(Level::DEBUG, "POST {url} {request_body}");
+    let response = http_client
+        .post(url)
+        .body(request_body)
+        .headers(headers)
+        .send()
+        .await?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let message = format!("{url} responded with {status}");
+    if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(crate::Error::Unavailable(message));
+    }
+
+    Err(crate::Error::Http(message))
+}
@@ -70,15 +70,21 @@ impl Digest {
     /// # Errors
     ///
     /// Will return `Err` if an invalid header is provided or if it uses an invalid algorithm.
-    pub fn from_header(dig: &str) -> Result<Self, String> {
+    pub fn from_header(dig: &str) -> crate::Result<Self> {
         dig.find('=').map_or_else(
-            || Err("Digest::from_header: invalid header".to_owned()),
+            || {
+                Err(crate::Error::Parse(
+                    "Digest::from_header: invalid header".to_owned(),
+                ))
+            },
             |pos| {
                 let pos = pos + 1;
                 if general_purpose::STANDARD.decode(&dig[pos..]).is_ok() {
                     Ok(Self(dig.to_owned()))
                 } else {
-                    Err("Digest::from_header: invalid algorithm".to_owned())
+                    Err(crate::Error::Parse(
+                        "Digest::from_header: invalid algorithm".to_owned(),
+                    ))
                 }
             },
         )
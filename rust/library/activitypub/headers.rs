@@ -1,4 +1,5 @@
 // copied from https://github.com/Plume-org/Plume/blob/main/plume-models/src/headers.rs
+use crate::settings::Settings;
 use rocket::http::{Header, HeaderMap};
 use rocket::request::{FromRequest, Outcome, Request};
 
@@ -22,6 +23,25 @@ impl<'r> FromRequest<'r> for Headers<'r> {
             "(request-target)",
             format!("{} {}", request.method().as_str().to_lowercase(), uri),
         ));
+        // derived components of RFC 9421 signatures
+        let authority = request.host().map(ToString::to_string).unwrap_or_default();
+        let base_url = request.rocket().state::<Settings>().map_or_else(
+            || format!("https://{authority}"),
+            |settings| settings.base_url.clone(),
+        );
+        let query = request.uri().query().map(|query| query.as_str().to_owned());
+        headers.add(Header::new("@method", request.method().as_str()));
+        headers.add(Header::new("@target-uri", format!("{base_url}{uri}")));
+        headers.add(Header::new("@authority", authority.to_lowercase()));
+        headers.add(Header::new(
+            "@path",
+            request.uri().path().as_str().to_owned(),
+        ));
+        headers.add(Header::new(
+            "@query",
+            format!("?{}", query.unwrap_or_default()),
+        ));
+        headers.add(Header::new("@request-target", uri));
         Outcome::Success(Headers(headers))
     }
 }
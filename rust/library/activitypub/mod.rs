@@ -1,7 +1,12 @@
+pub mod activity;
+pub mod collection;
 pub mod context;
+pub mod delivery;
 pub mod digest;
 pub mod headers;
+pub mod multikey;
 pub mod object;
 pub mod request;
 pub mod signature;
 pub mod verifier;
+pub mod webfinger;
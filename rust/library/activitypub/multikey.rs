@@ -0,0 +1,148 @@
+use crate::Error;
+use openssl::pkey::{Id, PKey, Public};
+
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+/// Multibase prefix of base58btc, the encoding of FEP-521a keys.
+const BASE58BTC: char = 'z';
+/// Multicodec of Ed25519 public keys, as an unsigned varint.
+const ED25519_PUB: [u8; 2] = [0xed, 0x01];
+
+/// Encodes the key as the `publicKeyMultibase` of a Multikey.
+///
+/// # Errors
+///
+/// Will return `Err` if the key isn't an Ed25519 key.
+pub fn encode(public_key: &PKey<Public>) -> crate::Result<String> {
+    if public_key.id() != Id::ED25519 {
+        return Err(Error::Parse("only Ed25519 keys are multikeys".to_owned()));
+    }
+
+    let mut bytes = ED25519_PUB.to_vec();
+    bytes.extend(public_key.raw_public_key()?);
+    Ok(format!("{BASE58BTC}{}", encode_base58(&bytes)))
+}
+
+/// # Errors
+///
+/// Will return `Err` if can´t parse the der public key or it isn't an Ed25519 key.
+pub fn der_to_multibase(der: &[u8]) -> crate::Result<String> {
+    encode(&PKey::public_key_from_der(der)?)
+}
+
+/// Decodes the `publicKeyMultibase` of a Multikey.
+///
+/// # Errors
+///
+/// Will return `Err` if it isn't a base58btc encoded Ed25519 key.
+pub fn decode(public_key_multibase: &str) -> crate::Result<PKey<Public>> {
+    let bytes = public_key_multibase
+        .strip_prefix(BASE58BTC)
+        .and_then(decode_base58)
+        .ok_or_else(|| Error::Parse(format!("invalid multibase {public_key_multibase}")))?;
+    let raw_public_key = bytes
+        .strip_prefix(&ED25519_PUB)
+        .ok_or_else(|| Error::Parse(format!("unsupported multikey {public_key_multibase}")))?;
+    Ok(PKey::public_key_from_raw_bytes(
+        raw_public_key,
+        Id::ED25519,
+    )?)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn encode_base58(bytes: &[u8]) -> String {
+    // little endian digits of the number that the bytes are in big endian
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in bytes {
+        let mut carry = u32::from(byte);
+        for digit in &mut digits {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    // each leading zero byte is a leading 1
+    let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+    std::iter::repeat_n(BASE58_ALPHABET[0], zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|&digit| BASE58_ALPHABET[digit as usize]),
+        )
+        .map(char::from)
+        .collect()
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn decode_base58(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+    for character in encoded.bytes() {
+        let mut carry = BASE58_ALPHABET
+            .iter()
+            .position(|&digit| digit == character)? as u32;
+        for byte in &mut bytes {
+            carry += u32::from(*byte) * 58;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+    let zeros = encoded
+        .bytes()
+        .take_while(|&character| character == BASE58_ALPHABET[0])
+        .count();
+    bytes.extend(std::iter::repeat_n(0, zeros));
+    bytes.reverse();
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_encode() {
+        // Arrange
+        let expected = "z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2";
+
+        // Act
+        let public_key = decode(expected).unwrap();
+        let actual = encode(&public_key).unwrap();
+
+        // Assert
+        assert_eq!(public_key.id(), Id::ED25519);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_base58_leading_zeros() {
+        // Arrange
+        let bytes = [0, 0, 1, 255];
+
+        // Act
+        let encoded = encode_base58(&bytes);
+
+        // Assert
+        assert_eq!(encoded, "119p");
+        assert_eq!(decode_base58(&encoded).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_decode_unsupported() {
+        // Arrange
+        let rsa_pub = [0x85, 0x24, 0x30, 0x82];
+
+        // Act
+        let actual = decode(&format!("z{}", encode_base58(&rsa_pub)));
+
+        // Assert
+        assert!(matches!(actual, Err(Error::Parse(_))));
+    }
+}
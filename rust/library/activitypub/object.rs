@@ -9,6 +9,12 @@ use std::fmt::Debug;
 pub struct Object {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub actor: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_multikeys",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub assertion_method: Option<Vec<Multikey>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub atom_uri: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -28,6 +34,8 @@ pub struct Object {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub discoverable: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoints: Option<Endpoints>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub followers: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub following: Option<String>,
@@ -82,6 +90,15 @@ pub struct Attachment {
     pub name: String,
 }
 
+/// Server-wide endpoints of an actor.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Endpoints {
+    /// Receives each activity once for all the actors of the server that it's addressed to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shared_inbox: Option<String>,
+}
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct PublicKey {
@@ -89,3 +106,32 @@ pub struct PublicKey {
     pub owner: String,
     pub public_key_pem: String,
 }
+
+/// A key in the `assertionMethod` of an actor, as described by FEP-521a.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Multikey {
+    pub id: String,
+    pub r#type: String,
+    pub controller: String,
+    pub public_key_multibase: String,
+}
+
+/// Keeps the Multikeys of an `assertionMethod`, which may be a single one, skipping
+/// the methods of other types and those only referenced by id.
+fn deserialize_multikeys<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<Multikey>>, D::Error> {
+    let methods = match Option::<serde_json::Value>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(serde_json::Value::Array(methods)) => methods,
+        Some(method) => vec![method],
+    };
+    Ok(Some(
+        methods
+            .into_iter()
+            .filter(|method| method["type"] == "Multikey")
+            .filter_map(|method| serde_json::from_value(method).ok())
+            .collect(),
+    ))
+}
@@ -1,5 +1,10 @@
+use crate::Error;
 use http::header::{HeaderMap, HeaderValue};
-use openssl::{hash::MessageDigest, pkey::PKey, rsa::Rsa, sign::Signer};
+use openssl::{
+    hash::MessageDigest,
+    pkey::{Id, PKey, Private},
+    sign::Signer,
+};
 use time::format_description::well_known::Rfc2822;
 use time::OffsetDateTime;
 
@@ -8,6 +13,12 @@ use tracing::{event, Level};
 
 const SELECT_HEADERS: &str = "(request-target) host date digest content-type";
 
+/// Adds the digest, date and signature headers of a request, signed with `rsa-sha256`
+/// by an RSA key and with `hs2019` by an Ed25519 key.
+///
+/// # Errors
+///
+/// Will return `Err` if the private key is invalid or a header value can´t be built.
 pub fn sign(
     method: &str,
     path: &str,
@@ -16,27 +27,29 @@ pub fn sign(
     private_key: &[u8],
     signature_key_id: &str,
     time_provider: &dyn crate::time_provider::TimeProvider,
-) {
-    insert_digest(all_headers, request_body);
-    insert_date(all_headers, time_provider);
-    insert_signature(method, path, all_headers, private_key, signature_key_id);
+) -> crate::Result<()> {
+    insert_digest(all_headers, request_body)?;
+    insert_date(all_headers, time_provider)?;
+    insert_signature(method, path, all_headers, private_key, signature_key_id)
 }
 
-fn insert_digest(all_headers: &mut HeaderMap, request_body: &str) {
+fn insert_digest(all_headers: &mut HeaderMap, request_body: &str) -> crate::Result<()> {
     let digest = super::digest::Digest::from_body(request_body);
-    let digest = HeaderValue::from_str(&digest).unwrap();
-    all_headers.insert("digest", digest);
+    all_headers.insert("digest", header_value(&digest)?);
+    Ok(())
 }
 
 fn insert_date(
     all_headers: &mut HeaderMap,
     time_provider: &dyn crate::time_provider::TimeProvider,
-) {
+) -> crate::Result<()> {
     let date: OffsetDateTime = time_provider.now_utc();
-    let date = date.format(&Rfc2822).unwrap();
+    let date = date
+        .format(&Rfc2822)
+        .map_err(|e| Error::Signature(format!("Failed to format date {e:?}")))?;
     let date = date.as_str().replace("+0000", "GMT");
-    let date = HeaderValue::from_str(&date).unwrap();
-    all_headers.insert("date", date);
+    all_headers.insert("date", header_value(&date)?);
+    Ok(())
 }
 
 fn insert_signature(
@@ -45,15 +58,26 @@ fn insert_signature(
     all_headers: &mut HeaderMap,
     private_key: &[u8],
     signature_key_id: &str,
-) {
+) -> crate::Result<()> {
     let select_headers = select_headers(method, path, all_headers, SELECT_HEADERS);
-    let signature = get_signature(private_key, &select_headers).unwrap();
+    let private_key = PKey::private_key_from_der(private_key)?;
+    let algorithm = if private_key.id() == Id::ED25519 {
+        "hs2019"
+    } else {
+        "rsa-sha256"
+    };
+    let signature = get_signature(&private_key, &select_headers)?;
     let signature = general_purpose::STANDARD.encode(signature);
     event!(Level::DEBUG, signature = signature);
-    let signature_header = format!("keyId=\"{signature_key_id}\",algorithm=\"rsa-sha256\",headers=\"{SELECT_HEADERS}\",signature=\"{signature}\"");
-    let signature_header = HeaderValue::from_str(signature_header.as_str()).unwrap();
-    all_headers.insert("signature", signature_header);
+    let signature_header = format!("keyId=\"{signature_key_id}\",algorithm=\"{algorithm}\",headers=\"{SELECT_HEADERS}\",signature=\"{signature}\"");
+    all_headers.insert("signature", header_value(&signature_header)?);
     event!(Level::DEBUG, all_headers = format!("{all_headers:?}"));
+    Ok(())
+}
+
+fn header_value(value: &str) -> crate::Result<HeaderValue> {
+    HeaderValue::from_str(value)
+        .map_err(|e| Error::Signature(format!("Invalid header value {value}: {e:?}")))
 }
 
 fn select_headers(method: &str, path: &str, all_headers: &HeaderMap, query: &str) -> String {
@@ -73,26 +97,25 @@ fn select_headers(method: &str, path: &str, all_headers: &HeaderMap, query: &str
             format!(
                 "{}: {}",
                 header.to_lowercase(),
-                all_headers.get(header).unwrap().to_str().unwrap_or("")
+                all_headers
+                    .get(header)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or("")
             )
         })
         .collect::<Vec<_>>()
         .join("\n")
 }
 
-fn get_signature(private_key: &[u8], to_sign: &str) -> Result<Vec<u8>, String> {
-    let key = PKey::from_rsa(
-        Rsa::private_key_from_der(private_key)
-            .map_err(|e| format!("Failed to private_key_from_der {e:?}"))?,
-    )
-    .map_err(|e| format!("Failed to from_rsa {e:?}"))?;
-    let mut signer = Signer::new(MessageDigest::sha256(), &key)
-        .map_err(|e| format!("Failed to create signer {e:?}"))?;
+fn get_signature(private_key: &PKey<Private>, to_sign: &str) -> crate::Result<Vec<u8>> {
     event!(Level::DEBUG, to_sign = to_sign);
-    signer
-        .update(to_sign.as_bytes())
-        .map_err(|e| format!("Failed to update signer {e:?}"))?;
-    signer
-        .sign_to_vec()
-        .map_err(|e| format!("Failed to sign_to_vec {e:?}"))
+    // Ed25519 hashes the message itself, in a single pass
+    if private_key.id() == Id::ED25519 {
+        let mut signer = Signer::new_without_digest(private_key)?;
+        return Ok(signer.sign_oneshot_to_vec(to_sign.as_bytes())?);
+    }
+
+    let mut signer = Signer::new(MessageDigest::sha256(), private_key)?;
+    signer.update(to_sign.as_bytes())?;
+    Ok(signer.sign_to_vec()?)
 }
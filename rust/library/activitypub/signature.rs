@@ -1,35 +1,57 @@
 // copied from https://github.com/Plume-org/Plume/blob/main/plume-common/src/activity_pub/sign.rs
 use super::digest::Digest;
+use super::verifier::Verifier;
+use crate::Error;
 use base64::{engine::general_purpose, Engine as _};
+use openssl::hash::{hash, MessageDigest};
 use rocket::http::HeaderMap;
 use time::format_description::well_known::Rfc2822;
 use time::{Duration, OffsetDateTime, PrimitiveDateTime};
 use tracing::{event, Level};
 
+/// The error of a signature that doesn't match the key, as opposed to a request that isn't
+/// acceptable whatever the key.
+const INVALID_SIGNATURE: &str = "invalid signature";
+
+/// The RFC 9421 algorithms of the RSA and Ed25519 keys that actors have.
+const RFC9421_ALGORITHMS: [&str; 2] = ["rsa-v1_5-sha256", "ed25519"];
+
+/// How an inbound request was signed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scheme {
+    /// `Signature` header of draft-cavage-http-signatures with `rsa-sha256`.
+    Cavage,
+    /// The same header with `hs2019`, where the algorithm is the one of the key.
+    Hs2019,
+    /// `Signature-Input` and `Signature` headers of RFC 9421.
+    Rfc9421,
+}
+
+#[derive(Default, Debug)]
 pub struct SignatureHeader {
+    pub key_id: Option<String>,
     pub algorithm: Option<String>,
     pub headers: Option<String>,
     pub signature: Option<String>,
+    pub created: Option<i64>,
+    pub expires: Option<i64>,
 }
 
 #[must_use]
 pub fn parse_header(signature_header: &str) -> SignatureHeader {
-    let mut result: SignatureHeader = SignatureHeader {
-        algorithm: None,
-        headers: None,
-        signature: None,
-    };
-    for part in signature_header.split(',') {
-        match part {
-            part if part.starts_with("algorithm=") => {
-                result.algorithm = Some(part[11..part.len() - 1].to_owned());
-            }
-            part if part.starts_with("headers=") => {
-                result.headers = Some(part[9..part.len() - 1].to_owned());
-            }
-            part if part.starts_with("signature=") => {
-                result.signature = Some(part[11..part.len() - 1].to_owned());
-            }
+    let mut result = SignatureHeader::default();
+    for part in split_outside_quotes(signature_header, ',') {
+        let Some((name, value)) = part.split_once('=') else {
+            continue;
+        };
+        let value = unquote(value.trim());
+        match name.trim() {
+            "keyId" => result.key_id = Some(value.to_owned()),
+            "algorithm" => result.algorithm = Some(value.to_owned()),
+            "headers" => result.headers = Some(value.to_owned()),
+            "signature" => result.signature = Some(value.to_owned()),
+            "created" => result.created = value.parse().ok(),
+            "expires" => result.expires = value.parse().ok(),
             _ => {}
         }
     }
@@ -37,86 +59,323 @@ pub fn parse_header(signature_header: &str) -> SignatureHeader {
     result
 }
 
-/// # Panics
+/// Gets the `keyId` of the signature, by which the key that made it is looked up.
+///
+/// # Errors
+///
+/// Will return [`Error::Signature`] if the request isn't signed or the signature has no key id.
+pub fn get_key_id(all_headers: &HeaderMap<'_>) -> crate::Result<String> {
+    let key_id = if all_headers.contains("Signature-Input") {
+        let (input, _) = get_rfc9421_signature(all_headers)?;
+        SignatureInput::parse(&input)?.key_id.map(ToOwned::to_owned)
+    } else {
+        let signature_header = all_headers
+            .get_one("Signature")
+            .ok_or_else(|| invalid("missing signature header"))?;
+        parse_header(signature_header).key_id
+    };
+    key_id.ok_or_else(|| invalid("missing keyId"))
+}
+
+/// Whether the request failed to verify only because the key didn't make its signature,
+/// so that another key of the sender may still verify it.
+#[must_use]
+pub fn is_invalid_signature(err: &Error) -> bool {
+    matches!(err, Error::Signature(message) if message == INVALID_SIGNATURE)
+}
+
+/// Verifies that the request was signed by `sender`, covering the digest of `body` and
+/// a recent date, in any of the schemes used by other servers.
 ///
-/// Will panic if it can´t parse the date in the header.
-pub fn is_valid<S: super::verifier::Verifier + ::std::fmt::Debug>(
+/// # Errors
+///
+/// Will return [`Error::Signature`] if the request isn't signed in a supported scheme,
+/// the signature is invalid, or it doesn't cover the target of the request, a matching digest
+/// and a recent date.
+pub fn verify<S: Verifier>(
+    sender: &S,
+    all_headers: &HeaderMap<'_>,
+    body: &str,
+) -> crate::Result<Scheme> {
+    let scheme = if all_headers.contains("Signature-Input") {
+        verify_rfc9421(sender, all_headers, body)?;
+        Scheme::Rfc9421
+    } else {
+        verify_cavage(sender, all_headers, body)?
+    };
+    event!(Level::DEBUG, "valid {scheme:?} signature");
+    Ok(scheme)
+}
+
+fn verify_cavage<S: Verifier>(
     sender: &S,
     all_headers: &HeaderMap<'_>,
-    content: &Digest,
-) -> bool {
-    event!(Level::DEBUG, "verify_http_headers");
-    let signature_header = all_headers.get_one("Signature");
-    if signature_header.is_none() {
-        event!(Level::DEBUG, "missing signature header");
-        return false;
-    }
-    let signature_header = signature_header.expect("sign::verify_http_headers: unreachable");
+    body: &str,
+) -> crate::Result<Scheme> {
+    let signature_header = all_headers
+        .get_one("Signature")
+        .ok_or_else(|| invalid("missing signature header"))?;
     let signature_header = parse_header(signature_header);
-    if signature_header.signature.is_none() || signature_header.headers.is_none() {
-        event!(Level::DEBUG, "missing part of headers");
-        return false;
-    }
+    let scheme = match signature_header.algorithm.as_deref() {
+        None | Some("rsa-sha256") => Scheme::Cavage,
+        Some("hs2019") => Scheme::Hs2019,
+        Some(algorithm) => return Err(invalid(&format!("unsupported algorithm {algorithm}"))),
+    };
     let signature = signature_header
         .signature
-        .expect("sign::verify_http_headers: unreachable");
-    event!(Level::DEBUG, signature = signature);
-    let signature = general_purpose::STANDARD
-        .decode(signature)
-        .expect("sign::verify_http_headers: can't decode signature");
-    let headers = signature_header.headers.unwrap();
-    let select_headers = super::headers::select(all_headers, headers.as_str());
-    event!(Level::DEBUG, select_headers = select_headers);
+        .as_deref()
+        .ok_or_else(|| invalid("missing signature"))?;
+    let default_headers = if scheme == Scheme::Hs2019 {
+        "(created)"
+    } else {
+        "date"
+    };
+    let headers = signature_header
+        .headers
+        .as_deref()
+        .unwrap_or(default_headers)
+        .to_lowercase();
+    let headers = headers.split_whitespace().collect::<Vec<_>>();
+    let signing_string = headers
+        .iter()
+        .map(|&name| {
+            let value = match name {
+                "(created)" => signature_header.created.map(|created| created.to_string()),
+                "(expires)" => signature_header.expires.map(|expires| expires.to_string()),
+                _ => get_header(all_headers, name),
+            };
+            value
+                .map(|value| format!("{name}: {value}"))
+                .ok_or_else(|| invalid(&format!("missing signed {name}")))
+        })
+        .collect::<crate::Result<Vec<_>>>()?
+        .join("\n");
+    verify_signature(sender, &signing_string, signature)?;
+
+    // otherwise the request could be sent again to another inbox
+    if !headers.contains(&"(request-target)") {
+        return Err(invalid("request target not signed"));
+    }
+    if !headers.contains(&"host") {
+        return Err(invalid("host not signed"));
+    }
+    if !headers.contains(&"digest") {
+        return Err(invalid("digest not signed"));
+    }
+    verify_digest(all_headers, body)?;
 
-    if !sender.verify(&select_headers, &signature).unwrap_or(false) {
-        event!(Level::DEBUG, "invalid signature");
-        return false;
+    if headers.contains(&"(created)") {
+        verify_timestamp(signature_header.created)?;
+    } else if headers.contains(&"date") {
+        verify_date_header(all_headers)?;
+    } else {
+        return Err(invalid("date not signed"));
     }
+    verify_expires(signature_header.expires)?;
+    Ok(scheme)
+}
 
-    if !headers.contains("digest") {
-        event!(Level::DEBUG, "valid no digest");
-        return false;
+fn verify_rfc9421<S: Verifier>(
+    sender: &S,
+    all_headers: &HeaderMap<'_>,
+    body: &str,
+) -> crate::Result<()> {
+    let (input, signature) = get_rfc9421_signature(all_headers)?;
+    let input = SignatureInput::parse(&input)?;
+    if input
+        .algorithm
+        .is_some_and(|algorithm| !RFC9421_ALGORITHMS.contains(&algorithm))
+    {
+        return Err(invalid("unsupported algorithm"));
     }
-    let digest_validity = verify_digest(all_headers, content);
-    if !digest_validity {
-        return false;
+
+    let mut signature_base = input
+        .components
+        .iter()
+        .map(|component| {
+            // derived components are added to the headers by the request guard
+            let value = if component.starts_with('@') {
+                all_headers.get_one(component).map(ToOwned::to_owned)
+            } else {
+                get_header(all_headers, component)
+            };
+            value
+                .map(|value| format!("\"{component}\": {value}"))
+                .ok_or_else(|| invalid(&format!("missing signed {component}")))
+        })
+        .collect::<crate::Result<Vec<_>>>()?;
+    signature_base.push(format!("\"@signature-params\": {}", input.raw));
+    verify_signature(sender, &signature_base.join("\n"), &signature)?;
+
+    if !input.covers("@target-uri")
+        && !["@method", "@authority", "@path"]
+            .iter()
+            .all(|component| input.covers(component))
+    {
+        return Err(invalid("target not signed"));
     }
+    if !input.covers("content-digest") {
+        return Err(invalid("content-digest not signed"));
+    }
+    verify_content_digest(all_headers, body)?;
 
-    if !headers.contains("date") {
-        event!(Level::DEBUG, "valid no date");
-        return false;
+    if input.created.is_some() {
+        verify_timestamp(input.created)?;
+    } else if input.covers("date") {
+        verify_date_header(all_headers)?;
+    } else {
+        return Err(invalid("creation not signed"));
     }
+    verify_expires(input.expires)
+}
+
+/// The input and the value of the first signature that has both.
+fn get_rfc9421_signature(all_headers: &HeaderMap<'_>) -> crate::Result<(String, String)> {
+    let signature_input = get_header(all_headers, "signature-input").unwrap_or_default();
+    let signatures = get_header(all_headers, "signature").unwrap_or_default();
+    let signatures = parse_dictionary(&signatures);
+    let (input, signature) = parse_dictionary(&signature_input)
+        .into_iter()
+        .find_map(|(label, input)| {
+            signatures
+                .iter()
+                .find(|(signature_label, _)| *signature_label == label)
+                .map(|(_, signature)| (input, *signature))
+        })
+        .ok_or_else(|| invalid("missing signature"))?;
+    let signature = signature
+        .strip_prefix(':')
+        .and_then(|signature| signature.strip_suffix(':'))
+        .ok_or_else(|| invalid("signature isn't a byte sequence"))?;
+    Ok((input.to_owned(), signature.to_owned()))
+}
 
-    verify_date_header(all_headers)
+/// The covered components and parameters of a signature in `Signature-Input`.
+struct SignatureInput<'a> {
+    raw: &'a str,
+    components: Vec<&'a str>,
+    algorithm: Option<&'a str>,
+    key_id: Option<&'a str>,
+    created: Option<i64>,
+    expires: Option<i64>,
 }
 
-pub fn verify_digest(all_headers: &HeaderMap<'_>, content: &Digest) -> bool {
-    let digest = all_headers.get_one("digest").unwrap_or("");
-    let digest = Digest::from_header(digest);
-    if !digest.map(|d| d.verify_header(content)).unwrap_or(false) {
-        event!(Level::DEBUG, "valid but digest doesn't match");
-        return false;
+impl<'a> SignatureInput<'a> {
+    fn parse(raw: &'a str) -> crate::Result<Self> {
+        let (components, parameters) = raw
+            .strip_prefix('(')
+            .and_then(|raw| raw.split_once(')'))
+            .ok_or_else(|| invalid("signature input isn't an inner list"))?;
+        let components = components
+            .split_whitespace()
+            .map(|component| {
+                component
+                    .strip_prefix('"')
+                    .and_then(|component| component.strip_suffix('"'))
+                    .ok_or_else(|| invalid(&format!("unsupported component {component}")))
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        let mut input = Self {
+            raw,
+            components,
+            algorithm: None,
+            key_id: None,
+            created: None,
+            expires: None,
+        };
+        for parameter in split_outside_quotes(parameters, ';') {
+            let Some((name, value)) = parameter.split_once('=') else {
+                continue;
+            };
+            match name.trim() {
+                "alg" => input.algorithm = Some(unquote(value.trim())),
+                "keyid" => input.key_id = Some(unquote(value.trim())),
+                "created" => input.created = value.trim().parse().ok(),
+                "expires" => input.expires = value.trim().parse().ok(),
+                _ => {}
+            }
+        }
+        Ok(input)
     }
 
-    true
+    fn covers(&self, component: &str) -> bool {
+        self.components.contains(&component)
+    }
 }
 
-pub fn verify_date_header(all_headers: &HeaderMap<'_>) -> bool {
-    let date = all_headers.get_one("date");
-    if date.is_none() {
-        event!(Level::DEBUG, "missing date header");
-        return false;
+fn verify_signature<S: Verifier>(sender: &S, data: &str, signature: &str) -> crate::Result<()> {
+    event!(Level::DEBUG, data = data, signature = signature);
+    let signature = general_purpose::STANDARD
+        .decode(signature)
+        .map_err(|_| invalid("can't decode signature"))?;
+    if sender.verify(data, &signature).unwrap_or(false) {
+        Ok(())
+    } else {
+        Err(invalid(INVALID_SIGNATURE))
     }
-    let date = PrimitiveDateTime::parse(date.unwrap(), &Rfc2822);
-    let date = if let Ok(date) = date {
-        date.assume_utc()
+}
+
+/// # Errors
+///
+/// Will return `Err` if the `Digest` header is missing or doesn't match `body`.
+pub fn verify_digest(all_headers: &HeaderMap<'_>, body: &str) -> crate::Result<()> {
+    let digest = all_headers
+        .get_one("digest")
+        .ok_or_else(|| invalid("missing digest header"))?;
+    if Digest::from_header(digest)?.verify(body) {
+        Ok(())
     } else {
-        event!(Level::DEBUG, "invalid date header");
-        return false;
-    };
-    verify_date(date)
+        Err(invalid("digest doesn't match"))
+    }
+}
+
+/// Every SHA-256 and SHA-512 value in the `Content-Digest` header must match `body`,
+/// and there must be at least one.
+fn verify_content_digest(all_headers: &HeaderMap<'_>, body: &str) -> crate::Result<()> {
+    let content_digest =
+        get_header(all_headers, "content-digest").ok_or_else(|| invalid("missing digest"))?;
+    let mut verified = false;
+    for (algorithm, value) in parse_dictionary(&content_digest) {
+        let message_digest = match algorithm.as_str() {
+            "sha-256" => MessageDigest::sha256(),
+            "sha-512" => MessageDigest::sha512(),
+            _ => continue,
+        };
+        let expected = hash(message_digest, body.as_bytes())?;
+        let value = value
+            .strip_prefix(':')
+            .and_then(|value| value.strip_suffix(':'))
+            .and_then(|value| general_purpose::STANDARD.decode(value).ok());
+        if value.as_deref() != Some(expected.as_ref()) {
+            return Err(invalid("digest doesn't match"));
+        }
+        verified = true;
+    }
+    if verified {
+        Ok(())
+    } else {
+        Err(invalid("unsupported digest"))
+    }
+}
+
+/// # Errors
+///
+/// Will return `Err` if the `Date` header is missing, invalid or too far from now.
+pub fn verify_date_header(all_headers: &HeaderMap<'_>) -> crate::Result<()> {
+    let date = all_headers
+        .get_one("date")
+        .ok_or_else(|| invalid("missing date header"))?;
+    let date = PrimitiveDateTime::parse(date, &Rfc2822)
+        .map_err(|_| invalid("invalid date header"))?
+        .assume_utc();
+    if verify_date(date) {
+        Ok(())
+    } else {
+        Err(invalid("date is too far from now"))
+    }
 }
 
+#[must_use]
 pub fn verify_date(date: OffsetDateTime) -> bool {
     let diff = OffsetDateTime::now_utc() - date;
     let future = Duration::hours(12);
@@ -129,3 +388,360 @@ pub fn verify_date(date: OffsetDateTime) -> bool {
         false
     }
 }
+
+fn verify_timestamp(unix_time_seconds: Option<i64>) -> crate::Result<()> {
+    let date = unix_time_seconds
+        .and_then(|seconds| OffsetDateTime::from_unix_timestamp(seconds).ok())
+        .ok_or_else(|| invalid("invalid creation time"))?;
+    if verify_date(date) {
+        Ok(())
+    } else {
+        Err(invalid("creation time is too far from now"))
+    }
+}
+
+fn verify_expires(unix_time_seconds: Option<i64>) -> crate::Result<()> {
+    match unix_time_seconds {
+        Some(expires) if expires < OffsetDateTime::now_utc().unix_timestamp() => {
+            Err(invalid("signature expired"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The values of a header sent more than once are combined as a list.
+fn get_header(all_headers: &HeaderMap<'_>, name: &str) -> Option<String> {
+    let values = all_headers.get(name).map(str::trim).collect::<Vec<_>>();
+    (!values.is_empty()).then(|| values.join(", "))
+}
+
+/// The members of a structured field dictionary, with their values as sent.
+fn parse_dictionary(field: &str) -> Vec<(String, &str)> {
+    split_outside_quotes(field, ',')
+        .into_iter()
+        .filter_map(|member| member.split_once('='))
+        .map(|(key, value)| (key.trim().to_lowercase(), value.trim()))
+        .collect()
+}
+
+fn split_outside_quotes(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (index, character) in value.char_indices() {
+        if character == '"' {
+            quoted = !quoted;
+        } else if character == separator && !quoted {
+            parts.push(&value[start..index]);
+            start = index + 1;
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Signature(message.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::pkey::{PKey, Private};
+    use openssl::rsa::Rsa;
+    use openssl::sign::Signer;
+    use rocket::http::Header;
+
+    const BODY: &str = r#"{"type":"Follow"}"#;
+
+    fn sign(key: &PKey<Private>, data: &str) -> String {
+        let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+        signer.update(data.as_bytes()).unwrap();
+        general_purpose::STANDARD.encode(signer.sign_to_vec().unwrap())
+    }
+
+    fn get_headers(names: &[(&'static str, String)]) -> HeaderMap<'static> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in names {
+            headers.add(Header::new(*name, value.clone()));
+        }
+        headers
+    }
+
+    fn date() -> String {
+        OffsetDateTime::now_utc()
+            .format(&Rfc2822)
+            .unwrap()
+            .replace("+0000", "GMT")
+    }
+
+    fn key() -> (PKey<Private>, PKey<openssl::pkey::Public>) {
+        let private_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let public_key =
+            PKey::public_key_from_der(&private_key.public_key_to_der().unwrap()).unwrap();
+        (private_key, public_key)
+    }
+
+    #[test]
+    fn test_parse_header() {
+        // Act
+        let actual = parse_header(
+            r#"keyId="https://example.com/users/a#main-key", algorithm="hs2019",created=1402170695,headers="(request-target) (created) digest",signature="YWJj=""#,
+        );
+
+        // Assert
+        assert_eq!(
+            actual.key_id.as_deref(),
+            Some("https://example.com/users/a#main-key")
+        );
+        assert_eq!(actual.algorithm.as_deref(), Some("hs2019"));
+        assert_eq!(actual.created, Some(1_402_170_695));
+        assert_eq!(
+            actual.headers.as_deref(),
+            Some("(request-target) (created) digest")
+        );
+        assert_eq!(actual.signature.as_deref(), Some("YWJj="));
+    }
+
+    #[test]
+    fn test_verify_cavage() {
+        // Arrange
+        let (private_key, public_key) = key();
+        let date = date();
+        let digest = Digest::from_body(BODY);
+        let signing_string = format!(
+            "(request-target): post /users/a/inbox\nhost: example.com\ndate: {date}\ndigest: {digest}"
+        );
+        let signature = format!(
+            r#"keyId="https://remote.example/users/b#main-key",algorithm="rsa-sha256",headers="(request-target) host date digest",signature="{}""#,
+            sign(&private_key, &signing_string)
+        );
+        let headers = get_headers(&[
+            ("(request-target)", "post /users/a/inbox".to_owned()),
+            ("host", "example.com".to_owned()),
+            ("date", date),
+            ("digest", digest),
+            ("signature", signature),
+        ]);
+
+        // Act
+        let actual = verify(&public_key, &headers, BODY);
+        let tampered = verify(&public_key, &headers, r#"{"type":"Undo"}"#);
+
+        // Assert
+        assert_eq!(actual.unwrap(), Scheme::Cavage);
+        assert!(matches!(tampered, Err(Error::Signature(_))));
+        assert_eq!(
+            get_key_id(&headers).unwrap(),
+            "https://remote.example/users/b#main-key"
+        );
+    }
+
+    #[test]
+    fn test_verify_hs2019() {
+        // Arrange
+        let (private_key, public_key) = key();
+        let created = OffsetDateTime::now_utc().unix_timestamp();
+        let digest = Digest::from_body(BODY);
+        let signing_string = format!(
+            "(request-target): post /users/a/inbox\nhost: example.com\n(created): {created}\ndigest: {digest}"
+        );
+        let signature = format!(
+            r#"keyId="https://remote.example/users/b#main-key",algorithm="hs2019",created={created},headers="(request-target) host (created) digest",signature="{}""#,
+            sign(&private_key, &signing_string)
+        );
+        let headers = get_headers(&[
+            ("(request-target)", "post /users/a/inbox".to_owned()),
+            ("host", "example.com".to_owned()),
+            ("digest", digest),
+            ("signature", signature),
+        ]);
+
+        // Act
+        let actual = verify(&public_key, &headers, BODY);
+
+        // Assert
+        assert_eq!(actual.unwrap(), Scheme::Hs2019);
+    }
+
+    #[test]
+    fn test_verify_ed25519() {
+        // Arrange
+        let private_key = PKey::generate_ed25519().unwrap();
+        let public_key =
+            PKey::public_key_from_der(&private_key.public_key_to_der().unwrap()).unwrap();
+        let mut signed_headers = http::HeaderMap::new();
+        signed_headers.insert("host", http::HeaderValue::from_static("example.com"));
+        signed_headers.insert(
+            "content-type",
+            http::HeaderValue::from_static("application/activity+json"),
+        );
+        crate::activitypub::request::sign(
+            "POST",
+            "/users/a/inbox",
+            &mut signed_headers,
+            BODY,
+            &private_key.private_key_to_der().unwrap(),
+            "https://remote.example/users/b#ed25519-key",
+            &OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+        let mut names = vec![("(request-target)", "post /users/a/inbox".to_owned())];
+        for name in ["host", "date", "digest", "content-type", "signature"] {
+            names.push((name, signed_headers[name].to_str().unwrap().to_owned()));
+        }
+        let headers = get_headers(&names);
+
+        // Act
+        let actual = verify(&public_key, &headers, BODY);
+        let rsa = verify(&key().1, &headers, BODY);
+
+        // Assert
+        assert_eq!(actual.unwrap(), Scheme::Hs2019);
+        assert!(matches!(rsa, Err(Error::Signature(_))));
+    }
+
+    #[test]
+    fn test_verify_rfc9421() {
+        // Arrange
+        let (private_key, public_key) = key();
+        let created = OffsetDateTime::now_utc().unix_timestamp();
+        let content_digest = format!(
+            "sha-256=:{}:",
+            general_purpose::STANDARD
+                .encode(hash(MessageDigest::sha256(), BODY.as_bytes()).unwrap())
+        );
+        let signature_params = format!(
+            r#"("@method" "@target-uri" "content-digest");created={created};keyid="https://remote.example/users/b#main-key";alg="rsa-v1_5-sha256""#
+        );
+        let signature_base = format!(
+            "\"@method\": POST\n\"@target-uri\": https://example.com/users/a/inbox\n\"content-digest\": {content_digest}\n\"@signature-params\": {signature_params}"
+        );
+        let headers = get_headers(&[
+            ("@method", "POST".to_owned()),
+            (
+                "@target-uri",
+                "https://example.com/users/a/inbox".to_owned(),
+            ),
+            ("content-digest", content_digest),
+            ("signature-input", format!("sig1={signature_params}")),
+            (
+                "signature",
+                format!("sig1=:{}:", sign(&private_key, &signature_base)),
+            ),
+        ]);
+
+        // Act
+        let actual = verify(&public_key, &headers, BODY);
+        let tampered = verify(&public_key, &headers, r#"{"type":"Undo"}"#);
+
+        // Assert
+        assert_eq!(actual.unwrap(), Scheme::Rfc9421);
+        assert_eq!(
+            get_key_id(&headers).unwrap(),
+            "https://remote.example/users/b#main-key"
+        );
+        assert!(matches!(tampered, Err(Error::Signature(_))));
+    }
+
+    #[test]
+    fn test_verify_unsigned_digest() {
+        // Arrange
+        let (private_key, public_key) = key();
+        let date = date();
+        let signature = format!(
+            r#"keyId="https://remote.example/users/b#main-key",headers="(request-target) host date",signature="{}""#,
+            sign(
+                &private_key,
+                &format!("(request-target): post /users/a/inbox\nhost: example.com\ndate: {date}")
+            )
+        );
+        let headers = get_headers(&[
+            ("(request-target)", "post /users/a/inbox".to_owned()),
+            ("host", "example.com".to_owned()),
+            ("date", date),
+            ("digest", Digest::from_body(BODY)),
+            ("signature", signature),
+        ]);
+
+        // Act
+        let actual = verify(&public_key, &headers, BODY);
+
+        // Assert
+        assert!(matches!(actual, Err(Error::Signature(message)) if message == "digest not signed"));
+    }
+
+    #[test]
+    fn test_verify_unsigned_request_target() {
+        // Arrange
+        let (private_key, public_key) = key();
+        let date = date();
+        let digest = Digest::from_body(BODY);
+        let signature = format!(
+            r#"keyId="https://remote.example/users/b#main-key",headers="host date digest",signature="{}""#,
+            sign(
+                &private_key,
+                &format!("host: example.com\ndate: {date}\ndigest: {digest}")
+            )
+        );
+        let headers = get_headers(&[
+            ("(request-target)", "post /users/b/inbox".to_owned()),
+            ("host", "example.com".to_owned()),
+            ("date", date),
+            ("digest", digest),
+            ("signature", signature),
+        ]);
+
+        // Act
+        let actual = verify(&public_key, &headers, BODY);
+
+        // Assert
+        assert!(
+            matches!(actual, Err(Error::Signature(message)) if message == "request target not signed")
+        );
+    }
+
+    #[test]
+    fn test_verify_rfc9421_unsigned_target() {
+        // Arrange
+        let (private_key, public_key) = key();
+        let created = OffsetDateTime::now_utc().unix_timestamp();
+        let content_digest = format!(
+            "sha-256=:{}:",
+            general_purpose::STANDARD
+                .encode(hash(MessageDigest::sha256(), BODY.as_bytes()).unwrap())
+        );
+        let signature_params = format!(
+            r#"("@method" "content-digest");created={created};keyid="https://remote.example/users/b#main-key""#
+        );
+        let signature_base = format!(
+            "\"@method\": POST\n\"content-digest\": {content_digest}\n\"@signature-params\": {signature_params}"
+        );
+        let headers = get_headers(&[
+            ("@method", "POST".to_owned()),
+            ("content-digest", content_digest),
+            ("signature-input", format!("sig1={signature_params}")),
+            (
+                "signature",
+                format!("sig1=:{}:", sign(&private_key, &signature_base)),
+            ),
+        ]);
+
+        // Act
+        let actual = verify(&public_key, &headers, BODY);
+
+        // Assert
+        assert!(matches!(actual, Err(Error::Signature(message)) if message == "target not signed"));
+    }
+}
@@ -3,5 +3,5 @@ pub trait Verifier {
     /// # Errors
     ///
     /// Will return `Err` if is unable to perform the verification.
-    fn verify(&self, data: &str, signature: &[u8]) -> Result<bool, String>;
+    fn verify(&self, data: &str, signature: &[u8]) -> crate::Result<bool>;
 }
@@ -0,0 +1,121 @@
+use crate::Error;
+use serde_json::Value;
+use tracing::{event, Level};
+
+/// Splits `user@domain` or `@user@domain` into its user and domain.
+///
+/// # Errors
+///
+/// Will return `Err` if the handle isn't in one of those forms.
+pub fn parse_handle(handle: &str) -> crate::Result<(&str, &str)> {
+    let handle = handle.strip_prefix('@').unwrap_or(handle);
+    match handle.split_once('@') {
+        Some((user, domain)) if !user.is_empty() && !domain.is_empty() && !domain.contains('@') => {
+            Ok((user, domain))
+        }
+        _ => Err(Error::Parse(format!("invalid handle {handle}"))),
+    }
+}
+
+#[must_use]
+pub fn get_url(user: &str, domain: &str) -> String {
+    let protocol = if domain.starts_with("localhost") {
+        "http"
+    } else {
+        "https"
+    };
+    format!("{protocol}://{domain}/.well-known/webfinger?resource=acct:{user}@{domain}")
+}
+
+/// Finds the actor id among the links of a `WebFinger` response.
+#[must_use]
+pub fn find_actor_id(document: &Value) -> Option<String> {
+    document["links"]
+        .as_array()?
+        .iter()
+        .find(|link| {
+            link["rel"] == "self"
+                && link["type"].as_str().is_some_and(|media_type| {
+                    media_type == "application/activity+json"
+                        || media_type.starts_with("application/ld+json")
+                })
+        })
+        .and_then(|link| link["href"].as_str())
+        .map(ToOwned::to_owned)
+}
+
+/// Resolves a remote handle to its actor id.
+///
+/// # Errors
+///
+/// Will return `Err` if the `WebFinger` request fails or it doesn't link to an actor.
+pub async fn resolve(handle: &str) -> crate::Result<String> {
+    let (user, domain) = parse_handle(handle)?;
+    let url = get_url(user, domain);
+    let response = crate::http_client::get()?
+        .get(&url)
+        .header(reqwest::header::ACCEPT, "application/jrd+json")
+        .send()
+        .await?
+        .error_for_status()?;
+    let text = response.text().await?;
+    event!(Level::DEBUG, text);
+    let document: Value = serde_json::from_str(&text)?;
+    find_actor_id(&document)
+        .ok_or_else(|| Error::NotFound(format!("{handle} has no ActivityPub actor")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_handle() {
+        assert_eq!(
+            parse_handle("@user@example.com"),
+            Ok(("user", "example.com"))
+        );
+        assert_eq!(
+            parse_handle("user@example.com"),
+            Ok(("user", "example.com"))
+        );
+        assert!(parse_handle("user").is_err());
+        assert!(parse_handle("@example.com").is_err());
+        assert!(parse_handle("user@").is_err());
+    }
+
+    #[test]
+    fn test_get_url() {
+        assert_eq!(
+            get_url("user", "example.com"),
+            "https://example.com/.well-known/webfinger?resource=acct:user@example.com"
+        );
+        assert_eq!(
+            get_url("user", "localhost:8080"),
+            "http://localhost:8080/.well-known/webfinger?resource=acct:user@localhost:8080"
+        );
+    }
+
+    #[test]
+    fn test_find_actor_id() {
+        // Arrange
+        let document = serde_json::json!({
+            "subject": "acct:user@example.com",
+            "links": [{
+                "rel": "http://webfinger.net/rel/profile-page",
+                "type": "text/html",
+                "href": "https://example.com/@user"
+            }, {
+                "rel": "self",
+                "type": "application/activity+json",
+                "href": "https://example.com/users/user"
+            }]
+        });
+
+        // Act
+        let actual = find_actor_id(&document);
+
+        // Assert
+        assert_eq!(actual.as_deref(), Some("https://example.com/users/user"));
+    }
+}
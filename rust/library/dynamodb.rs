@@ -1,68 +1,11 @@
-use aws_sdk_dynamodb::error::SdkError;
-use aws_sdk_dynamodb::operation::get_item::{GetItemError, GetItemOutput};
-use aws_sdk_dynamodb::operation::put_item::{PutItemError, PutItemOutput};
 use aws_sdk_dynamodb::types::{
-    AttributeDefinition, AttributeValue, KeySchemaElement, KeyType, ProvisionedThroughput,
-    ScalarAttributeType,
+    AttributeDefinition, KeySchemaElement, KeyType, ProvisionedThroughput, ScalarAttributeType,
+    StreamSpecification, StreamViewType,
 };
 use aws_sdk_dynamodb::Client;
-use std::collections::HashMap;
 use tracing::{event, Level};
 
-pub const PARTITION_KEY_NAME: &str = "partition_key";
-pub const SORT_KEY_NAME: &str = "sort_key";
-
-pub type GetItemResult = Result<GetItemOutput, SdkError<GetItemError>>;
-pub type PutItemResult = Result<PutItemOutput, SdkError<PutItemError>>;
-
-/// # Errors
-///
-/// Will return `Err` if a connection to the database is no properly established.
-pub async fn get_item(
-    client: &Client,
-    dynamodb_table_name: &str,
-    partition: &str,
-    sort_value: &str,
-    projection_expression: &str,
-) -> GetItemResult {
-    event!(Level::DEBUG, "Get item: table {dynamodb_table_name} partition {partition} sort_value {sort_value} projection_expression {projection_expression}");
-    client
-        .get_item()
-        .table_name(dynamodb_table_name)
-        .key(PARTITION_KEY_NAME, AttributeValue::S(partition.to_owned()))
-        .key(SORT_KEY_NAME, AttributeValue::S(sort_value.to_owned()))
-        .projection_expression(projection_expression)
-        .send()
-        .await
-}
-
-/// # Errors
-///
-/// Will return `Err` if a connection to the database is no properly established.
-pub async fn put_item<S: std::hash::BuildHasher, T: serde::Serialize + std::marker::Send>(
-    client: &Client,
-    dynamodb_table_name: &str,
-    partition: &str,
-    sort_value: &str,
-    values: T,
-) -> PutItemResult
-where
-    HashMap<std::string::String, AttributeValue, S>: From<serde_dynamo::Item>,
-{
-    let mut table = client
-        .put_item()
-        .table_name(dynamodb_table_name)
-        .item(PARTITION_KEY_NAME, AttributeValue::S(partition.to_owned()))
-        .item(SORT_KEY_NAME, AttributeValue::S(sort_value.to_owned()));
-    {
-        let values: HashMap<String, AttributeValue, S> = serde_dynamo::to_item(values).unwrap();
-        for (key, value) in values {
-            table = table.item(key, value);
-        }
-    }
-
-    table.send().await
-}
+pub use crate::store::{PARTITION_KEY_NAME, SORT_KEY_NAME};
 
 pub async fn get_client() -> Client {
     if let Ok(url) = std::env::var("LOCAL_DYNAMODB_URL") {
@@ -84,22 +27,24 @@ pub async fn get_local_client(local_dynamodb_url: String) -> Client {
     Client::from_conf(dynamodb_local_config)
 }
 
-async fn table_exists(client: &aws_sdk_dynamodb::Client, table: &str) -> bool {
-    let table_list = client.list_tables().send().await.unwrap();
+async fn table_exists(client: &aws_sdk_dynamodb::Client, table: &str) -> crate::Result<bool> {
+    let table_list = client.list_tables().send().await?;
     event!(Level::DEBUG, "tables {table_list:?}");
-    table_list
+    Ok(table_list
         .table_names()
-        .as_ref()
-        .unwrap()
-        .contains(&table.into())
+        .unwrap_or_default()
+        .contains(&table.into()))
 }
 
-/// # Panics
+/// # Errors
 ///
-/// Will panic if can´t create the table.
-pub async fn create_table_if_not_exists(client: &aws_sdk_dynamodb::Client, table_name: &str) {
-    if table_exists(client, table_name).await {
-        return;
+/// Will return `Err` if can´t list or create the tables.
+pub async fn create_table_if_not_exists(
+    client: &aws_sdk_dynamodb::Client,
+    table_name: &str,
+) -> crate::Result<()> {
+    if table_exists(client, table_name).await? {
+        return Ok(());
     }
 
     let partition_attribute_definition = AttributeDefinition::builder()
@@ -127,6 +72,12 @@ pub async fn create_table_if_not_exists(client: &aws_sdk_dynamodb::Client, table
         .write_capacity_units(5)
         .build();
 
+    // as in cloudformation.yml, for the stream Lambda to run locally
+    let stream_specification = StreamSpecification::builder()
+        .stream_enabled(true)
+        .stream_view_type(StreamViewType::NewAndOldImages)
+        .build();
+
     client
         .create_table()
         .table_name(table_name)
@@ -135,7 +86,8 @@ pub async fn create_table_if_not_exists(client: &aws_sdk_dynamodb::Client, table
         .attribute_definitions(partition_attribute_definition)
         .attribute_definitions(sort_attribute_definition)
         .provisioned_throughput(pt)
+        .stream_specification(stream_specification)
         .send()
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
@@ -0,0 +1,173 @@
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{self, Responder};
+use std::fmt;
+use tracing::{event, Level};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors returned by the library, each one mapped to an HTTP status.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// The database failed or couldn´t be reached.
+    Storage(String),
+    /// A request to a remote server failed.
+    Http(String),
+    /// A remote server timed out or answered that it may work later, so the request can be retried.
+    Unavailable(String),
+    /// A signature couldn´t be created or verified.
+    Signature(String),
+    /// Some input, local or remote, isn't in the expected format.
+    Parse(String),
+    /// The input is well formed but not acceptable, like a `Follow` of another user.
+    Invalid(String),
+    NotFound(String),
+    /// The resource exists but its owner chose not to share it.
+    Forbidden(String),
+}
+
+impl Error {
+    #[must_use]
+    pub const fn status(&self) -> Status {
+        match self {
+            Self::Storage(_) => Status::InternalServerError,
+            Self::Http(_) => Status::BadGateway,
+            Self::Unavailable(_) => Status::ServiceUnavailable,
+            Self::Signature(_) => Status::Unauthorized,
+            Self::Parse(_) | Self::Invalid(_) => Status::BadRequest,
+            Self::NotFound(_) => Status::NotFound,
+            Self::Forbidden(_) => Status::Forbidden,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Storage(message) => write!(f, "storage error: {message}"),
+            Self::Http(message) => write!(f, "HTTP error: {message}"),
+            Self::Unavailable(message) => write!(f, "unavailable: {message}"),
+            Self::Signature(message) => write!(f, "signature error: {message}"),
+            Self::Parse(message) => write!(f, "parse error: {message}"),
+            Self::Invalid(message) => write!(f, "invalid request: {message}"),
+            Self::NotFound(message) => write!(f, "not found: {message}"),
+            Self::Forbidden(message) => write!(f, "forbidden: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl<E: fmt::Debug, R: fmt::Debug> From<aws_sdk_dynamodb::error::SdkError<E, R>> for Error {
+    fn from(err: aws_sdk_dynamodb::error::SdkError<E, R>) -> Self {
+        Self::Storage(format!("{err:?}"))
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Self::Storage(err.to_string())
+    }
+}
+
+impl From<serde_dynamo::Error> for Error {
+    fn from(err: serde_dynamo::Error) -> Self {
+        Self::Parse(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Parse(err.to_string())
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() || err.is_connect() {
+            return Self::Unavailable(err.to_string());
+        }
+
+        Self::Http(err.to_string())
+    }
+}
+
+impl From<openssl::error::ErrorStack> for Error {
+    fn from(err: openssl::error::ErrorStack) -> Self {
+        Self::Signature(err.to_string())
+    }
+}
+
+/// Errors caused by the content of the request are answered with their message,
+/// the rest are left to the catcher of their status.
+impl<'r> Responder<'r, 'static> for Error {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let status = self.status();
+        match self {
+            Self::Parse(_) | Self::Invalid(_) | Self::Signature(_) => {
+                event!(
+                    Level::DEBUG,
+                    "{} {}: {self}",
+                    request.method(),
+                    request.uri()
+                );
+                response::status::Custom(status, self.to_string()).respond_to(request)
+            }
+            Self::NotFound(_) | Self::Forbidden(_) => {
+                event!(
+                    Level::DEBUG,
+                    "{} {}: {self}",
+                    request.method(),
+                    request.uri()
+                );
+                Err(status)
+            }
+            Self::Storage(_) | Self::Http(_) | Self::Unavailable(_) => {
+                event!(
+                    Level::ERROR,
+                    "{} {}: {self}",
+                    request.method(),
+                    request.uri()
+                );
+                Err(status)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status() {
+        assert_eq!(
+            Error::Storage("timeout".to_owned()).status(),
+            Status::InternalServerError
+        );
+        assert_eq!(
+            Error::Signature("invalid".to_owned()).status(),
+            Status::Unauthorized
+        );
+        assert_eq!(
+            Error::NotFound("user".to_owned()).status(),
+            Status::NotFound
+        );
+        assert_eq!(
+            Error::Unavailable("timeout".to_owned()).status(),
+            Status::ServiceUnavailable
+        );
+    }
+
+    #[test]
+    fn test_from_serde_json() {
+        // Arrange
+        let err = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+
+        // Act
+        let actual = Error::from(err);
+
+        // Assert
+        assert!(matches!(actual, Error::Parse(_)));
+    }
+}
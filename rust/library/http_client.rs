@@ -0,0 +1,25 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const CONNECT_TIMEOUT_SECONDS: u64 = 5;
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// The client of every request to other servers, so that connections are reused and none of
+/// them waits on an unresponsive server for longer than the timeouts.
+///
+/// # Errors
+///
+/// Will return `Err` if the client can´t be built.
+pub fn get() -> crate::Result<&'static reqwest::Client> {
+    if let Some(client) = CLIENT.get() {
+        return Ok(client);
+    }
+
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECONDS))
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+        .build()?;
+    Ok(CLIENT.get_or_init(|| client))
+}
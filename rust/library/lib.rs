@@ -1,8 +1,14 @@
 pub mod activitypub;
 pub mod dynamodb;
+pub mod error;
 pub mod faas_snowflake_id;
+pub mod http_client;
 pub mod model;
 pub mod rsa;
 pub mod settings;
+pub mod store;
+pub mod stream;
 pub mod time_provider;
 pub mod trace;
+
+pub use error::{Error, Result};
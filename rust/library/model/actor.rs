@@ -1,37 +1,282 @@
 use crate::activitypub::object::Object;
+use crate::activitypub::signature::{self, Scheme};
 use crate::settings::Settings;
+use crate::store::Store;
+use crate::Error;
 use openssl::{
     hash::MessageDigest,
-    pkey::{PKey, Public},
+    pkey::{Id, PKey, Public},
     rsa::Rsa,
     sign::Verifier,
 };
 
+use rocket::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
 use tracing::{event, Level};
 
-pub async fn get_public_key(
-    actor_id: &str,
+/// How long after fetching a remote actor again it isn't fetched again, so that
+/// requests with invalid signatures don't make it fetch the actor over and over.
+const REFETCH_COOLDOWN_SECONDS: u64 = 10 * 60;
+
+/// When a remote actor was last fetched again, to replace its cached keys.
+#[derive(Serialize, Deserialize)]
+struct Refetch {
+    unix_time_seconds: u64,
+}
+
+/// A key that signs requests, with the actor it belongs to.
+pub struct SigningKey {
+    pub owner: String,
+    /// The key, followed by those it recently replaced.
+    pub public_keys: Vec<PKey<Public>>,
+}
+
+impl SigningKey {
+    /// Verifies the signature of the request with the key or, failing that,
+    /// with those it replaced.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if none of the keys verifies the request.
+    pub fn verify(&self, all_headers: &HeaderMap<'_>, body: &str) -> crate::Result<Scheme> {
+        let mut result = Err(Error::Signature(format!("no key of {}", self.owner)));
+        for public_key in &self.public_keys {
+            result = signature::verify(public_key, all_headers, body);
+            // past the signature, the request is as acceptable with any other key
+            if !result.as_ref().is_err_and(signature::is_invalid_signature) {
+                break;
+            }
+        }
+        result
+    }
+}
+
+/// Why the cached keys didn't verify a request.
+enum Failure {
+    /// The key isn't in the cached document or didn't make the signature, as when the actor
+    /// rotated its keys.
+    Key(Error),
+    /// The request isn't acceptable whatever the key, like with an old date or another digest.
+    Request(Error),
+}
+
+impl Failure {
+    fn into_error(self) -> Error {
+        match self {
+            Self::Key(err) | Self::Request(err) => err,
+        }
+    }
+}
+
+/// Looks up the key by the `keyId` of a signature, in the document it identifies,
+/// which is the actor itself or a key that its owner must claim as their own.
+///
+/// # Errors
+///
+/// Will return `Err` if the key can´t be found, is invalid or isn't its owner's key.
+pub async fn get_signing_key(
+    key_id: &str,
     settings: &rocket::State<Settings>,
-) -> Result<PKey<Public>, String> {
-    let domain = get_domain(actor_id)?;
-    if domain == settings.domain_name {
-        let url_split = actor_id.split('/').collect::<Vec<&str>>();
+) -> crate::Result<SigningKey> {
+    let document_id = key_id.split('#').next().unwrap_or_default();
+    if get_domain(document_id)? == settings.domain_name {
+        let url_split = document_id.split('/').collect::<Vec<&str>>();
         let username = url_split[url_split.len() - 1];
-        return super::user::get_public_key(username, settings).await;
+        if key_id != format!("{document_id}#main-key")
+            && key_id != super::user::ed25519_key_id(document_id)
+        {
+            return Err(Error::Signature(format!("unknown key {key_id}")));
+        }
+        return Ok(SigningKey {
+            owner: document_id.to_owned(),
+            public_keys: super::user::get_public_keys(username, key_id, settings).await?,
+        });
+    }
+
+    let store = settings.store.as_ref();
+    let signing_key = get_key(document_id, key_id, store).await?;
+    if signing_key.owner != document_id {
+        get_key(&signing_key.owner, key_id, store).await?;
+    }
+    Ok(signing_key)
+}
+
+/// The key of the document with `key_id`, its RSA `publicKey` or a Multikey
+/// of its `assertionMethod`.
+async fn get_key(document_id: &str, key_id: &str, store: &dyn Store) -> crate::Result<SigningKey> {
+    let document = get_from_cache(document_id, store).await?;
+    if let Some(public_key) = document
+        .public_key
+        .filter(|public_key| public_key.id == key_id)
+    {
+        let rsa = Rsa::public_key_from_pem(public_key.public_key_pem.as_bytes())?;
+        return Ok(SigningKey {
+            owner: public_key.owner,
+            public_keys: vec![PKey::from_rsa(rsa)?],
+        });
+    }
+
+    // the key replaced by a rotation follows the current one with the same id for a while
+    let multikeys = document
+        .assertion_method
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|multikey| multikey.id == key_id)
+        .collect::<Vec<_>>();
+    let owner = multikeys
+        .first()
+        .map(|multikey| multikey.controller.clone())
+        .ok_or_else(|| Error::Signature(format!("{document_id} doesn't have the key {key_id}")))?;
+    let public_keys = multikeys
+        .iter()
+        .filter(|multikey| multikey.controller == owner)
+        .map(|multikey| crate::activitypub::multikey::decode(&multikey.public_key_multibase))
+        .collect::<crate::Result<_>>()?;
+    Ok(SigningKey { owner, public_keys })
+}
+
+/// Verifies that the request was signed by the actor with its key `key_id`.
+///
+/// The cached keys of a remote actor are out of date after it rotates them, so when the key
+/// isn't found or doesn't match the signature, the actor is fetched again and the
+/// verification retried, at most once every [`REFETCH_COOLDOWN_SECONDS`] per actor.
+///
+/// # Errors
+///
+/// Will return [`Error::Signature`] if the key isn't the actor's or doesn't verify the request.
+pub async fn verify_signature(
+    actor_id: &str,
+    key_id: &str,
+    all_headers: &HeaderMap<'_>,
+    body: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<Scheme> {
+    let document_id = key_id.split('#').next().unwrap_or_default();
+    let is_local = get_domain(document_id)? == settings.domain_name;
+    let err = match verify_with_cache(actor_id, key_id, all_headers, body, settings).await {
+        Ok(scheme) => return Ok(scheme),
+        Err(Failure::Key(err)) if !is_local => err,
+        Err(failure) => return Err(failure.into_error()),
+    };
+
+    let now = OffsetDateTime::now_utc().unix_timestamp().unsigned_abs();
+    let mut document_ids = vec![document_id];
+    if actor_id != document_id {
+        document_ids.push(actor_id);
+    }
+    let mut refetched = false;
+    for document_id in document_ids {
+        match refetch(document_id, settings.store.as_ref(), now).await {
+            Ok(is_refetched) => refetched |= is_refetched,
+            Err(err) => event!(Level::WARN, "Refetching {document_id}: {err}"),
+        }
     }
-    if let Some(actor) = get_from_cache(actor_id, settings).await {
-        let public_key = actor.public_key.unwrap();
-        let rsa = Rsa::public_key_from_pem(public_key.public_key_pem.as_bytes()).unwrap();
-        return PKey::from_rsa(rsa).map_err(|err| format!("failed to parse public key: {err:?}"));
+    if !refetched {
+        return Err(err);
+    }
+
+    event!(
+        Level::DEBUG,
+        "Verifying again with the refetched key {key_id}"
+    );
+    verify_with_cache(actor_id, key_id, all_headers, body, settings)
+        .await
+        .map_err(Failure::into_error)
+}
+
+async fn verify_with_cache(
+    actor_id: &str,
+    key_id: &str,
+    all_headers: &HeaderMap<'_>,
+    body: &str,
+    settings: &rocket::State<Settings>,
+) -> Result<Scheme, Failure> {
+    let signing_key = get_signing_key(key_id, settings)
+        .await
+        .map_err(|err| match err {
+            Error::Signature(_) => Failure::Key(err),
+            err => Failure::Request(err),
+        })?;
+    if signing_key.owner != actor_id {
+        return Err(Failure::Request(Error::Signature(format!(
+            "{actor_id} signed with the key {key_id} of {}",
+            signing_key.owner
+        ))));
+    }
+    signing_key.verify(all_headers, body).map_err(|err| {
+        if signature::is_invalid_signature(&err) {
+            Failure::Key(err)
+        } else {
+            Failure::Request(err)
+        }
+    })
+}
+
+/// Fetches the actor again into the cache, unless it was already refetched during
+/// the cooldown, returning whether it did.
+async fn refetch(actor_id: &str, store: &dyn Store, now: u64) -> crate::Result<bool> {
+    let partition = format!("actor/{}/refetches", get_domain(actor_id)?);
+    let last: Option<Refetch> =
+        crate::store::get(store, &partition, actor_id, &["unix_time_seconds"]).await?;
+    if last.is_some_and(|last| now < last.unix_time_seconds + REFETCH_COOLDOWN_SECONDS) {
+        event!(Level::DEBUG, "{actor_id} was refetched recently");
+        return Ok(false);
     }
 
-    Err("actor not found".to_owned())
+    // saved first, so that an actor that can't be fetched isn't tried again either
+    let refetch = Refetch {
+        unix_time_seconds: now,
+    };
+    crate::store::put(store, &partition, actor_id, &refetch).await?;
+    let actor = get_from_url(actor_id).await?;
+    create(actor_id, &actor, store).await?;
+    Ok(true)
 }
 
-fn get_domain(actor_id: &str) -> Result<&str, String> {
+/// # Errors
+///
+/// Will return `Err` if the actor can´t be fetched or doesn't advertise an inbox.
+pub async fn get_inbox(
+    actor_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<String> {
+    get_from_cache(actor_id, settings.store.as_ref())
+        .await?
+        .inbox
+        .ok_or_else(|| Error::NotFound(format!("inbox of actor {actor_id}")))
+}
+
+/// Gets where to deliver activities for the actor, and whether its server verifies Ed25519.
+///
+/// The inbox is the one shared by the actors of the server when it has one, which verifies
+/// Ed25519 signatures when it publishes the Ed25519 keys of its actors.
+///
+/// # Errors
+///
+/// Will return `Err` if the actor can´t be fetched or doesn't advertise an inbox.
+pub async fn get_delivery_inbox(
+    actor_id: &str,
+    store: &dyn Store,
+) -> crate::Result<(String, bool)> {
+    let actor = get_from_cache(actor_id, store).await?;
+    let ed25519 = actor.assertion_method.is_some_and(|multikeys| {
+        multikeys.iter().any(|multikey| {
+            crate::activitypub::multikey::decode(&multikey.public_key_multibase).is_ok()
+        })
+    });
+    let inbox = actor
+        .endpoints
+        .and_then(|endpoints| endpoints.shared_inbox)
+        .or(actor.inbox)
+        .ok_or_else(|| Error::NotFound(format!("inbox of actor {actor_id}")))?;
+    Ok((inbox, ed25519))
+}
+
+fn get_domain(actor_id: &str) -> crate::Result<&str> {
     let url_split = actor_id.split('/').collect::<Vec<&str>>();
     if url_split.len() < 4 {
-        return Err("invalid actor id".to_owned());
+        return Err(Error::Parse(format!("invalid actor id {actor_id}")));
     }
 
     Ok(url_split[2])
@@ -39,84 +284,332 @@ fn get_domain(actor_id: &str) -> Result<&str, String> {
 
 impl crate::activitypub::verifier::Verifier for PKey<Public> {
     /// Verify if the signature is valid
-    fn verify(&self, data: &str, signature: &[u8]) -> Result<bool, String> {
+    fn verify(&self, data: &str, signature: &[u8]) -> crate::Result<bool> {
         event!(
             Level::DEBUG,
-            public_key = hex::encode(self.public_key_to_der().unwrap())
+            public_key = hex::encode(self.public_key_to_der()?)
         );
-        let mut verifier = Verifier::new(MessageDigest::sha256(), self)
-            .map_err(|e| format!("Failed to create verifier {e:?}"))?;
-        verifier
-            .update(data.as_bytes())
-            .map_err(|e| format!("Failed to update verifier {e:?}"))?;
-        verifier
-            .verify(signature)
-            .map_err(|e| format!("Failed to verify {e:?}"))
+        if self.id() == Id::ED25519 {
+            let mut verifier = Verifier::new_without_digest(self)?;
+            return Ok(verifier.verify_oneshot(signature, data.as_bytes())?);
+        }
+
+        let mut verifier = Verifier::new(MessageDigest::sha256(), self)?;
+        verifier.update(data.as_bytes())?;
+        Ok(verifier.verify(signature)?)
     }
 }
 
-async fn get_from_cache(actor_id: &str, settings: &rocket::State<Settings>) -> Option<Object> {
-    if let Some(actor) = get_from_db(actor_id, settings).await {
-        return Some(actor);
+async fn get_from_cache(actor_id: &str, store: &dyn Store) -> crate::Result<Object> {
+    if let Some(actor) = get_from_db(actor_id, store).await? {
+        return Ok(actor);
     }
 
-    let actor = get_from_url(actor_id).await;
-    create(actor_id, &actor, settings).await.ok()?;
-    Some(actor)
+    let actor = get_from_url(actor_id).await?;
+    create(actor_id, &actor, store).await?;
+    Ok(actor)
 }
 
-async fn get_from_db(actor_id: &str, settings: &rocket::State<Settings>) -> Option<Object> {
-    let domain = get_domain(actor_id).unwrap();
+async fn get_from_db(actor_id: &str, store: &dyn Store) -> crate::Result<Option<Object>> {
+    let domain = get_domain(actor_id)?;
     let partition = format!("actor/{domain}");
-    let get_item_output = crate::dynamodb::get_item(
-        &settings.db_client,
-        &settings.table_name,
+    crate::store::get(
+        store,
         partition.as_str(),
         actor_id,
-        "publicKey",
+        &["publicKey", "assertionMethod", "inbox", "endpoints"],
     )
     .await
-    .unwrap();
-    if let Some(item) = get_item_output.item {
-        let actor: Object = serde_dynamo::from_item(item).unwrap();
-        return Some(actor);
-    }
-
-    None
 }
 
-/// # Panics
-///
-/// Will panic if it insert the new row.
-async fn create(
-    actor_id: &str,
-    object: &Object,
-    settings: &rocket::State<Settings>,
-) -> Result<(), String> {
+async fn create(actor_id: &str, object: &Object, store: &dyn Store) -> crate::Result<()> {
     let domain = get_domain(actor_id)?;
     let partition = format!("actor/{domain}");
-    crate::dynamodb::put_item(
-        &settings.db_client,
-        &settings.table_name,
-        partition.as_str(),
-        actor_id,
-        object,
-    )
-    .await
-    .unwrap();
+    crate::store::put(store, partition.as_str(), actor_id, object).await?;
     Ok(())
 }
 
-async fn get_from_url(user_url: &str) -> Object {
-    let http_client = reqwest::Client::new();
-    let actual_response = http_client
+async fn get_from_url(user_url: &str) -> crate::Result<Object> {
+    let actual_response = crate::http_client::get()?
         .get(user_url)
         .header(reqwest::header::ACCEPT, "application/json")
         .send()
-        .await
-        .unwrap();
+        .await?
+        .error_for_status()?;
     event!(Level::DEBUG, "{actual_response:?}");
-    let text = &actual_response.text().await.unwrap();
+    let text = &actual_response.text().await?;
     event!(Level::DEBUG, text);
-    serde_json::from_str::<Object>(text).unwrap()
+    Ok(serde_json::from_str::<Object>(text)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn put_document(settings: &Settings, id: &str, key_id: &str, owner: &str) {
+        let public_key_pem = Rsa::generate(2048).unwrap().public_key_to_pem().unwrap();
+        let document = serde_json::json!({
+            "id": id,
+            "publicKey": {
+                "id": key_id,
+                "owner": owner,
+                "publicKeyPem": String::from_utf8(public_key_pem).unwrap(),
+            },
+        });
+        crate::store::put(
+            settings.store.as_ref(),
+            "actor/remote.example",
+            id,
+            &document,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_signing_key() {
+        // Arrange
+        let settings = Settings::in_memory();
+        let actor_id = "https://remote.example/users/b";
+        let key_id = "https://remote.example/users/b#main-key";
+        put_document(&settings, actor_id, key_id, actor_id).await;
+        let settings = <&rocket::State<Settings>>::from(&settings);
+
+        // Act
+        let actual = get_signing_key(key_id, settings).await.unwrap();
+
+        // Assert
+        assert_eq!(actual.owner, actor_id);
+    }
+
+    #[tokio::test]
+    async fn test_get_signing_key_unclaimed() {
+        // Arrange
+        let settings = Settings::in_memory();
+        let key_id = "https://remote.example/keys/1";
+        let actor_id = "https://remote.example/users/b";
+        put_document(&settings, key_id, key_id, actor_id).await;
+        put_document(
+            &settings,
+            actor_id,
+            "https://remote.example/keys/2",
+            actor_id,
+        )
+        .await;
+        let settings = <&rocket::State<Settings>>::from(&settings);
+
+        // Act
+        let actual = get_signing_key(key_id, settings).await;
+
+        // Assert
+        assert!(matches!(actual, Err(Error::Signature(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_signing_key_multikey() {
+        // Arrange
+        let settings = Settings::in_memory();
+        let actor_id = "https://remote.example/users/b";
+        let key_id = "https://remote.example/users/b#ed25519-key";
+        let private_key = PKey::generate_ed25519().unwrap();
+        let public_key_der = private_key.public_key_to_der().unwrap();
+        let previous_der = PKey::generate_ed25519()
+            .unwrap()
+            .public_key_to_der()
+            .unwrap();
+        let multikey = |der: &[u8]| {
+            serde_json::json!({
+                "id": key_id,
+                "type": "Multikey",
+                "controller": actor_id,
+                "publicKeyMultibase": crate::activitypub::multikey::der_to_multibase(der).unwrap(),
+            })
+        };
+        let actor = serde_json::json!({
+            "id": actor_id,
+            "assertionMethod": [
+                "https://remote.example/users/b#other-key",
+                multikey(&public_key_der),
+                multikey(&previous_der),
+            ],
+        });
+        crate::store::put(
+            settings.store.as_ref(),
+            "actor/remote.example",
+            actor_id,
+            &actor,
+        )
+        .await
+        .unwrap();
+        let settings = <&rocket::State<Settings>>::from(&settings);
+
+        // Act
+        let actual = get_signing_key(key_id, settings).await.unwrap();
+
+        // Assert
+        assert_eq!(actual.owner, actor_id);
+        assert_eq!(
+            actual
+                .public_keys
+                .iter()
+                .map(|public_key| public_key.public_key_to_der().unwrap())
+                .collect::<Vec<_>>(),
+            [public_key_der, previous_der]
+        );
+    }
+
+    fn signed_headers(private_key: &[u8], key_id: &str, body: &str) -> HeaderMap<'static> {
+        let mut signed_headers = http::HeaderMap::new();
+        signed_headers.insert("host", http::HeaderValue::from_static("example.com"));
+        signed_headers.insert(
+            "content-type",
+            http::HeaderValue::from_static("application/activity+json"),
+        );
+        crate::activitypub::request::sign(
+            "POST",
+            "/users/a/inbox",
+            &mut signed_headers,
+            body,
+            private_key,
+            key_id,
+            &OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.add_raw("(request-target)", "post /users/a/inbox");
+        for (name, value) in &signed_headers {
+            headers.add_raw(name.to_string(), value.to_str().unwrap().to_owned());
+        }
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_verify_signature_refetch() {
+        // Arrange
+        let settings = Settings::in_memory();
+        // nothing listens on the port, so that fetching the actor again fails
+        let actor_id = "http://127.0.0.1:9/users/b";
+        let key_id = "http://127.0.0.1:9/users/b#main-key";
+        crate::store::put(
+            settings.store.as_ref(),
+            "actor/127.0.0.1:9",
+            actor_id,
+            &serde_json::json!({
+                "id": actor_id,
+                "publicKey": {
+                    "id": key_id,
+                    "owner": actor_id,
+                    "publicKeyPem": String::from_utf8(
+                        Rsa::generate(2048).unwrap().public_key_to_pem().unwrap()
+                    )
+                    .unwrap(),
+                },
+            }),
+        )
+        .await
+        .unwrap();
+        let rotated_key = Rsa::generate(2048).unwrap().private_key_to_der().unwrap();
+        let body = r#"{"type":"Follow"}"#;
+        let headers = signed_headers(&rotated_key, key_id, body);
+        let settings = <&rocket::State<Settings>>::from(&settings);
+
+        // Act
+        let actual = verify_signature(actor_id, key_id, &headers, body, settings).await;
+
+        // Assert
+        assert!(matches!(actual, Err(Error::Signature(_))));
+        let refetch: Option<Refetch> = crate::store::get(
+            settings.store.as_ref(),
+            "actor/127.0.0.1:9/refetches",
+            actor_id,
+            &["unix_time_seconds"],
+        )
+        .await
+        .unwrap();
+        assert!(refetch.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_verify_signature_other_digest() {
+        // Arrange
+        let settings = Settings::in_memory();
+        let actor_id = "http://127.0.0.1:9/users/b";
+        let key_id = "http://127.0.0.1:9/users/b#main-key";
+        let keypair = Rsa::generate(2048).unwrap();
+        crate::store::put(
+            settings.store.as_ref(),
+            "actor/127.0.0.1:9",
+            actor_id,
+            &serde_json::json!({
+                "id": actor_id,
+                "publicKey": {
+                    "id": key_id,
+                    "owner": actor_id,
+                    "publicKeyPem": String::from_utf8(keypair.public_key_to_pem().unwrap())
+                        .unwrap(),
+                },
+            }),
+        )
+        .await
+        .unwrap();
+        // signed with the cached key, but for another body
+        let headers = signed_headers(
+            &keypair.private_key_to_der().unwrap(),
+            key_id,
+            r#"{"type":"Follow"}"#,
+        );
+        let settings = <&rocket::State<Settings>>::from(&settings);
+
+        // Act
+        let actual =
+            verify_signature(actor_id, key_id, &headers, r#"{"type":"Undo"}"#, settings).await;
+
+        // Assert
+        assert!(matches!(actual, Err(Error::Signature(message)) if message.contains("digest")));
+        let refetch: Option<Refetch> = crate::store::get(
+            settings.store.as_ref(),
+            "actor/127.0.0.1:9/refetches",
+            actor_id,
+            &["unix_time_seconds"],
+        )
+        .await
+        .unwrap();
+        assert!(refetch.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refetch_cooldown() {
+        // Arrange
+        let settings = Settings::in_memory();
+        let actor_id = "http://127.0.0.1:9/users/b";
+        crate::store::put(
+            settings.store.as_ref(),
+            "actor/127.0.0.1:9/refetches",
+            actor_id,
+            &Refetch {
+                unix_time_seconds: 1_000,
+            },
+        )
+        .await
+        .unwrap();
+
+        // Act
+        let actual = refetch(
+            actor_id,
+            settings.store.as_ref(),
+            1_000 + REFETCH_COOLDOWN_SECONDS - 1,
+        )
+        .await;
+        let expired = refetch(
+            actor_id,
+            settings.store.as_ref(),
+            1_000 + REFETCH_COOLDOWN_SECONDS,
+        )
+        .await;
+
+        // Assert
+        assert!(!actual.unwrap());
+        // the actor is fetched again once the cooldown is over, which fails here
+        assert!(expired.is_err());
+    }
 }
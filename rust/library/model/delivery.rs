@@ -0,0 +1,602 @@
+use crate::store::{AttributeValue, SortKeyCondition, Store};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+
+const QUEUE_PARTITION: &str = "deliveries";
+const FAN_OUT_PARTITION: &str = "fan_outs";
+const FIRST_RETRY_SECONDS: u64 = 60;
+const MAX_RETRY_SECONDS: u64 = 6 * 60 * 60;
+/// Longer than an invocation can run, so that a claimed delivery is only attempted again once
+/// the invocation that claimed it is gone.
+const LEASE_SECONDS: u64 = 15 * 60;
+
+/// How long a delivery is retried before giving up, unless configured otherwise.
+pub const DEFAULT_HORIZON_SECONDS: u64 = 2 * 24 * 60 * 60;
+
+/// An activity waiting in the queue to be posted to one inbox.
+///
+/// Jobs are sorted by the time of their next attempt, so the due ones come first.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Delivery {
+    pub username: String,
+    pub activity_id: String,
+    pub inbox: String,
+    pub body: String,
+    pub signature_key_id: String,
+    #[serde(default)]
+    pub attempts: u32,
+    pub enqueued_unix_time_seconds: u64,
+    pub next_attempt_unix_time_seconds: u64,
+    /// Until when the invocation that [`claim`]ed the delivery is attempting it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leased_until_unix_time_seconds: Option<u64>,
+}
+
+impl Delivery {
+    #[must_use]
+    pub fn new(
+        username: &str,
+        activity_id: &str,
+        inbox: &str,
+        body: String,
+        signature_key_id: String,
+        now: u64,
+    ) -> Self {
+        Self {
+            username: username.to_owned(),
+            activity_id: activity_id.to_owned(),
+            inbox: inbox.to_owned(),
+            body,
+            signature_key_id,
+            attempts: 0,
+            enqueued_unix_time_seconds: now,
+            next_attempt_unix_time_seconds: now,
+            leased_until_unix_time_seconds: None,
+        }
+    }
+
+    fn sort_value(&self) -> String {
+        format!(
+            "{:020} {} {}",
+            self.next_attempt_unix_time_seconds, self.activity_id, self.inbox
+        )
+    }
+
+    fn target_partition(&self) -> String {
+        format!("users/{}/deliveries", self.username)
+    }
+
+    fn target_sort_value(&self) -> String {
+        format!("{} {}", self.activity_id, self.inbox)
+    }
+}
+
+/// An activity still to be queued for the followers after `cursor`.
+///
+/// Accounts with many followers take several invocations to go through all of them.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct FanOut {
+    pub username: String,
+    pub activity_id: String,
+    pub body: String,
+    pub signature_key_id: String,
+    /// Sort value of the last follower whose delivery is queued.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+impl FanOut {
+    #[must_use]
+    pub fn delivery(&self, inbox: &str, now: u64) -> Delivery {
+        Delivery::new(
+            &self.username,
+            &self.activity_id,
+            inbox,
+            self.body.clone(),
+            self.signature_key_id.clone(),
+            now,
+        )
+    }
+}
+
+/// What happened to the delivery of an activity to an inbox.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum State {
+    Pending,
+    Delivered,
+    /// The inbox refused the activity or was unavailable for longer than the horizon.
+    Failed,
+}
+
+/// Kept once per activity and inbox, so that the same delivery is never queued twice.
+#[derive(Serialize, Deserialize)]
+struct Target {
+    state: State,
+    #[serde(default)]
+    attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Queues the delivery to be attempted right away, unless it was queued before.
+///
+/// The job goes in first, so that a target is never left pending without one. When two
+/// invocations queue the same delivery at once, the one that loses the target takes its job back.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t save the delivery.
+pub async fn enqueue(store: &dyn Store, delivery: &Delivery) -> crate::Result<bool> {
+    let sort_value = delivery.sort_value();
+    let item = serde_dynamo::to_item(delivery)?;
+    if !store
+        .put_new_item(QUEUE_PARTITION, &sort_value, item)
+        .await?
+    {
+        return Ok(false);
+    }
+
+    let target = Target {
+        state: State::Pending,
+        attempts: 0,
+        error: None,
+    };
+    let is_new = store
+        .put_new_item(
+            &delivery.target_partition(),
+            &delivery.target_sort_value(),
+            serde_dynamo::to_item(target)?,
+        )
+        .await?;
+    if !is_new {
+        store.take_item(QUEUE_PARTITION, &sort_value).await?;
+    }
+    Ok(is_new)
+}
+
+/// Leases the delivery to the caller before attempting it, returning whether it got it,
+/// so that invocations running at the same time never post it twice.
+///
+/// The delivery stays in the queue until it's [`complete`]d, so that it's attempted again
+/// once the lease ends if the invocation stops before, as when it times out.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t update the queue.
+pub async fn claim(store: &dyn Store, delivery: &mut Delivery, now: u64) -> crate::Result<bool> {
+    if delivery
+        .leased_until_unix_time_seconds
+        .is_some_and(|leased_until| now < leased_until)
+    {
+        return Ok(false);
+    }
+
+    let leased = Delivery {
+        leased_until_unix_time_seconds: Some(now.saturating_add(LEASE_SECONDS)),
+        ..delivery.clone()
+    };
+    let expected = delivery
+        .leased_until_unix_time_seconds
+        .map(|leased_until| AttributeValue::N(leased_until.to_string()));
+    let is_leased = store
+        .replace_item_if(
+            QUEUE_PARTITION,
+            &delivery.sort_value(),
+            serde_dynamo::to_item(&leased)?,
+            "leased_until_unix_time_seconds",
+            expected.as_ref(),
+        )
+        .await?;
+    if is_leased {
+        *delivery = leased;
+    }
+    Ok(is_leased)
+}
+
+/// Takes a [`claim`]ed delivery out of the queue without attempting it, as when it was
+/// delivered before.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t update the queue.
+pub async fn discard(store: &dyn Store, delivery: &Delivery) -> crate::Result<()> {
+    store
+        .delete_item(QUEUE_PARTITION, &delivery.sort_value())
+        .await
+}
+
+/// Whether the items of the partition are the bookkeeping of the deliveries.
+#[must_use]
+pub fn is_delivery_partition(partition: &str) -> bool {
+    partition == QUEUE_PARTITION
+        || partition == FAN_OUT_PARTITION
+        || partition
+            .strip_prefix("users/")
+            .and_then(|rest| rest.strip_suffix("/deliveries"))
+            .is_some_and(|username| !username.contains('/'))
+}
+
+/// Gets up to `limit` deliveries whose next attempt is due at `now`, the oldest first.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t read the queue.
+pub async fn get_due(store: &dyn Store, now: u64, limit: usize) -> crate::Result<Vec<Delivery>> {
+    let upper_bound = format!("{:020}", now.saturating_add(1));
+    let items = store
+        .get_range(
+            QUEUE_PARTITION,
+            SortKeyCondition::LessThan(&upper_bound),
+            true,
+            limit,
+        )
+        .await?;
+    crate::store::from_items(items)
+}
+
+/// # Errors
+///
+/// Will return `Err` if it can´t read the state.
+pub async fn get_state(store: &dyn Store, delivery: &Delivery) -> crate::Result<Option<State>> {
+    let target: Option<Target> = crate::store::get(
+        store,
+        &delivery.target_partition(),
+        &delivery.target_sort_value(),
+        &["state"],
+    )
+    .await?;
+    Ok(target.map(|target| target.state))
+}
+
+/// Records the attempt of a [`claim`]ed delivery and takes it out of the queue.
+///
+/// If the inbox was [`Error::Unavailable`], or the attempt failed on the [`Error::Storage`]
+/// that the delivery reads from, it's queued again with exponential backoff, for as long as
+/// the next attempt falls within `horizon_seconds` of the first one.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t update the queue.
+pub async fn complete(
+    store: &dyn Store,
+    delivery: &Delivery,
+    result: &crate::Result<()>,
+    now: u64,
+    horizon_seconds: u64,
+) -> crate::Result<State> {
+    let attempts = delivery.attempts + 1;
+    let next_attempt = now.saturating_add(backoff_seconds(delivery.attempts));
+    let horizon = delivery
+        .enqueued_unix_time_seconds
+        .saturating_add(horizon_seconds);
+    let state = match result {
+        Ok(()) => State::Delivered,
+        Err(Error::Unavailable(_) | Error::Storage(_)) if next_attempt <= horizon => {
+            let retry = Delivery {
+                attempts,
+                next_attempt_unix_time_seconds: next_attempt,
+                leased_until_unix_time_seconds: None,
+                ..delivery.clone()
+            };
+            crate::store::put(store, QUEUE_PARTITION, &retry.sort_value(), &retry).await?;
+            State::Pending
+        }
+        Err(_) => State::Failed,
+    };
+    let target = Target {
+        state,
+        attempts,
+        error: result.as_ref().err().map(ToString::to_string),
+    };
+    crate::store::put(
+        store,
+        &delivery.target_partition(),
+        &delivery.target_sort_value(),
+        &target,
+    )
+    .await?;
+    // removed last, so that the delivery is attempted again if anything before fails
+    discard(store, delivery).await?;
+    Ok(state)
+}
+
+/// Saves the fan-out with its cursor, replacing the previous one of the same activity.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t save the fan-out.
+pub async fn put_fan_out(store: &dyn Store, fan_out: &FanOut) -> crate::Result<()> {
+    crate::store::put(store, FAN_OUT_PARTITION, &fan_out.activity_id, fan_out).await
+}
+
+/// Moves the cursor of the fan-out to `cursor`, unless another invocation moved it first,
+/// returning whether it did.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t save the fan-out.
+pub async fn advance_fan_out(
+    store: &dyn Store,
+    fan_out: &mut FanOut,
+    cursor: String,
+) -> crate::Result<bool> {
+    let advanced = FanOut {
+        cursor: Some(cursor),
+        ..fan_out.clone()
+    };
+    let is_advanced = store
+        .replace_item_if(
+            FAN_OUT_PARTITION,
+            &fan_out.activity_id,
+            serde_dynamo::to_item(&advanced)?,
+            "cursor",
+            fan_out
+                .cursor
+                .as_ref()
+                .map(|cursor| AttributeValue::S(cursor.clone()))
+                .as_ref(),
+        )
+        .await?;
+    if is_advanced {
+        *fan_out = advanced;
+    }
+    Ok(is_advanced)
+}
+
+/// # Errors
+///
+/// Will return `Err` if it can´t read the fan-outs.
+pub async fn get_fan_outs(store: &dyn Store, limit: usize) -> crate::Result<Vec<FanOut>> {
+    let items = store
+        .get_range(FAN_OUT_PARTITION, SortKeyCondition::Any, true, limit)
+        .await?;
+    crate::store::from_items(items)
+}
+
+/// # Errors
+///
+/// Will return `Err` if it can´t delete the fan-out.
+pub async fn delete_fan_out(store: &dyn Store, fan_out: &FanOut) -> crate::Result<()> {
+    store
+        .delete_item(FAN_OUT_PARTITION, &fan_out.activity_id)
+        .await
+}
+
+/// One minute after the first attempt, doubling after each one up to six hours.
+fn backoff_seconds(attempts: u32) -> u64 {
+    FIRST_RETRY_SECONDS
+        .saturating_mul(2_u64.saturating_pow(attempts))
+        .min(MAX_RETRY_SECONDS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    fn delivery(now: u64) -> Delivery {
+        Delivery::new(
+            "a",
+            "https://example.com/users/a/statuses/1",
+            "https://remote.example/inbox",
+            "{}".to_owned(),
+            "https://example.com/users/a#main-key".to_owned(),
+            now,
+        )
+    }
+
+    #[test]
+    fn test_backoff_seconds() {
+        assert_eq!(backoff_seconds(0), 60);
+        assert_eq!(backoff_seconds(3), 480);
+        assert_eq!(backoff_seconds(100), MAX_RETRY_SECONDS);
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_once() {
+        // Arrange
+        let store = MemoryStore::new();
+        let delivery = delivery(1000);
+        enqueue(&store, &delivery).await.unwrap();
+        let mut due = get_due(&store, 1000, 10).await.unwrap();
+        claim(&store, &mut due[0], 1000).await.unwrap();
+        complete(&store, &due[0], &Ok(()), 1001, DEFAULT_HORIZON_SECONDS)
+            .await
+            .unwrap();
+
+        // Act
+        let actual = enqueue(&store, &delivery).await.unwrap();
+
+        // Assert
+        assert!(!actual);
+        assert_eq!(
+            get_state(&store, &delivery).await.unwrap(),
+            Some(State::Delivered)
+        );
+        assert!(get_due(&store, u64::MAX - 1, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_complete_unavailable() {
+        // Arrange
+        let store = MemoryStore::new();
+        let mut delivery = delivery(1000);
+        enqueue(&store, &delivery).await.unwrap();
+        let unavailable = Err(Error::Unavailable("503".to_owned()));
+
+        // Act
+        claim(&store, &mut delivery, 1000).await.unwrap();
+        let retried = complete(&store, &delivery, &unavailable, 1000, 600)
+            .await
+            .unwrap();
+        let not_due = get_due(&store, 1059, 10).await.unwrap();
+        let mut due = get_due(&store, 1060, 10).await.unwrap();
+        claim(&store, &mut due[0], 1500).await.unwrap();
+        let given_up = complete(&store, &due[0], &unavailable, 1500, 600)
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(retried, State::Pending);
+        assert!(not_due.is_empty());
+        assert_eq!(due[0].attempts, 1);
+        assert_eq!(given_up, State::Failed);
+        assert!(get_due(&store, u64::MAX - 1, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_complete_storage() {
+        // Arrange
+        let store = MemoryStore::new();
+        let mut delivery = delivery(1000);
+        enqueue(&store, &delivery).await.unwrap();
+        claim(&store, &mut delivery, 1000).await.unwrap();
+
+        // Act
+        let actual = complete(
+            &store,
+            &delivery,
+            &Err(Error::Storage("throttled".to_owned())),
+            1000,
+            DEFAULT_HORIZON_SECONDS,
+        )
+        .await
+        .unwrap();
+
+        // Assert
+        assert_eq!(actual, State::Pending);
+        assert_eq!(get_due(&store, 1060, 10).await.unwrap()[0].attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_same_target() {
+        // Arrange
+        let store = MemoryStore::new();
+        enqueue(&store, &delivery(1000)).await.unwrap();
+
+        // Act
+        let actual = enqueue(&store, &delivery(1001)).await.unwrap();
+
+        // Assert
+        assert!(!actual);
+        assert_eq!(get_due(&store, 1001, 10).await.unwrap(), [delivery(1000)]);
+    }
+
+    #[tokio::test]
+    async fn test_claim_lease() {
+        // Arrange
+        let store = MemoryStore::new();
+        enqueue(&store, &delivery(1000)).await.unwrap();
+        let mut claimed = get_due(&store, 1000, 10).await.unwrap();
+        let mut stale = claimed.clone();
+
+        // Act
+        let first = claim(&store, &mut claimed[0], 1000).await.unwrap();
+        let concurrent = claim(&store, &mut stale[0], 1000).await.unwrap();
+        // the invocation that claimed it stops without completing it
+        let mut due = get_due(&store, 1000 + LEASE_SECONDS - 1, 10).await.unwrap();
+        let leased = claim(&store, &mut due[0], 1000 + LEASE_SECONDS - 1)
+            .await
+            .unwrap();
+        let mut due = get_due(&store, 1000 + LEASE_SECONDS, 10).await.unwrap();
+        let expired = claim(&store, &mut due[0], 1000 + LEASE_SECONDS)
+            .await
+            .unwrap();
+
+        // Assert
+        assert!(first);
+        assert!(!concurrent);
+        assert!(!leased);
+        assert!(expired);
+        assert_eq!(
+            get_state(&store, &due[0]).await.unwrap(),
+            Some(State::Pending)
+        );
+    }
+
+    #[test]
+    fn test_is_delivery_partition() {
+        assert!(is_delivery_partition("deliveries"));
+        assert!(is_delivery_partition("fan_outs"));
+        assert!(is_delivery_partition("users/a/deliveries"));
+        assert!(!is_delivery_partition("users/a/statuses"));
+        assert!(!is_delivery_partition("users/a/b/deliveries"));
+    }
+
+    #[tokio::test]
+    async fn test_fan_out() {
+        // Arrange
+        let store = MemoryStore::new();
+        let mut fan_out = FanOut {
+            username: "a".to_owned(),
+            activity_id: "https://example.com/users/a/statuses/1".to_owned(),
+            body: "{}".to_owned(),
+            signature_key_id: "https://example.com/users/a#main-key".to_owned(),
+            cursor: None,
+        };
+        put_fan_out(&store, &fan_out).await.unwrap();
+
+        // Act
+        fan_out.cursor = Some("https://remote.example/users/b".to_owned());
+        put_fan_out(&store, &fan_out).await.unwrap();
+        let actual = get_fan_outs(&store, 10).await.unwrap();
+        delete_fan_out(&store, &fan_out).await.unwrap();
+
+        // Assert
+        assert_eq!(actual, [fan_out]);
+        assert!(get_fan_outs(&store, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_advance_fan_out() {
+        // Arrange
+        let store = MemoryStore::new();
+        let mut fan_out = FanOut {
+            username: "a".to_owned(),
+            activity_id: "https://example.com/users/a/statuses/1".to_owned(),
+            body: "{}".to_owned(),
+            signature_key_id: "https://example.com/users/a#main-key".to_owned(),
+            cursor: None,
+        };
+        put_fan_out(&store, &fan_out).await.unwrap();
+        let mut stale = fan_out.clone();
+
+        // Act
+        let first = advance_fan_out(&store, &mut fan_out, "b".to_owned())
+            .await
+            .unwrap();
+        let second = advance_fan_out(&store, &mut stale, "c".to_owned())
+            .await
+            .unwrap();
+
+        // Assert
+        assert!(first);
+        assert!(!second);
+        assert_eq!(stale.cursor, None);
+        assert_eq!(get_fan_outs(&store, 10).await.unwrap(), [fan_out]);
+    }
+
+    #[tokio::test]
+    async fn test_complete_refused() {
+        // Arrange
+        let store = MemoryStore::new();
+        let mut delivery = delivery(1000);
+        enqueue(&store, &delivery).await.unwrap();
+        claim(&store, &mut delivery, 1000).await.unwrap();
+
+        // Act
+        let actual = complete(
+            &store,
+            &delivery,
+            &Err(Error::Http("401 Unauthorized".to_owned())),
+            1000,
+            DEFAULT_HORIZON_SECONDS,
+        )
+        .await
+        .unwrap();
+
+        // Assert
+        assert_eq!(actual, State::Failed);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::settings::Settings;
+use crate::store::SortKeyCondition;
+use crate::Error;
+
+const FOLLOW_ATTRIBUTES: &[&str] = &["@context", "id", "type", "actor", "object"];
+
+/// Stores a `Follow` of a locked account until it is approved or rejected.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t insert the follow request.
+pub async fn create(
+    username: &str,
+    actor_id: &str,
+    follow: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    let partition = format!("users/{username}/follow_requests");
+    crate::store::put(
+        settings.store.as_ref(),
+        partition.as_str(),
+        actor_id,
+        follow,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Withdraws a pending `Follow`, if there is one.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t delete the follow request.
+pub async fn delete(
+    username: &str,
+    actor_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    let partition = format!("users/{username}/follow_requests");
+    settings
+        .store
+        .delete_item(partition.as_str(), actor_id)
+        .await?;
+    Ok(())
+}
+
+/// Withdraws the pending `Follow` of the actor if it's the one with `follow_id`,
+/// returning whether there was such a request.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t read or delete the follow request.
+pub async fn withdraw(
+    username: &str,
+    actor_id: &str,
+    follow_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<bool> {
+    let partition = format!("users/{username}/follow_requests");
+    let follow: Option<serde_json::Value> =
+        crate::store::get(settings.store.as_ref(), &partition, actor_id, &["id"]).await?;
+    if !follow.is_some_and(|follow| follow["id"] == follow_id) {
+        return Ok(false);
+    }
+
+    delete(username, actor_id, settings).await?;
+    Ok(true)
+}
+
+/// Lists the pending `Follow` activities of the user.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t query the follow requests.
+pub async fn list(
+    username: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<Vec<serde_json::Value>> {
+    let partition = format!("users/{username}/follow_requests");
+    let items = settings
+        .store
+        .get_range(&partition, SortKeyCondition::Any, true, usize::MAX)
+        .await?;
+    let mut follows: Vec<serde_json::Value> = crate::store::from_items(items)?;
+    for follow in &mut follows {
+        if let Some(follow) = follow.as_object_mut() {
+            follow.retain(|name, _| FOLLOW_ATTRIBUTES.contains(&name.as_str()));
+        }
+    }
+    Ok(follows)
+}
+
+/// Makes the requester a follower and queues an `Accept` for it.
+///
+/// The request is only removed once both are done, so that a failed approval can be retried.
+///
+/// # Errors
+///
+/// Will return `Err` if there is no such request or the `Accept` can´t be queued.
+pub async fn approve(
+    username: &str,
+    actor_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    let follow = get(username, actor_id, settings).await?;
+    super::follower::create(username, actor_id, &follow, settings).await?;
+    super::follower::accept(username, &follow, settings).await?;
+    delete(username, actor_id, settings).await
+}
+
+/// Discards the request once a `Reject` is queued for the requester.
+///
+/// # Errors
+///
+/// Will return `Err` if there is no such request or the `Reject` can´t be queued.
+pub async fn reject(
+    username: &str,
+    actor_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    let follow = get(username, actor_id, settings).await?;
+    super::follower::reject(username, &follow, settings).await?;
+    delete(username, actor_id, settings).await
+}
+
+async fn get(
+    username: &str,
+    actor_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<serde_json::Value> {
+    let partition = format!("users/{username}/follow_requests");
+    crate::store::get(
+        settings.store.as_ref(),
+        &partition,
+        actor_id,
+        FOLLOW_ATTRIBUTES,
+    )
+    .await?
+    .ok_or_else(|| Error::NotFound(format!("follow request from {actor_id}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_withdraw() {
+        // Arrange
+        let settings = Settings::in_memory();
+        let settings = <&rocket::State<Settings>>::from(&settings);
+        let actor_id = "https://remote.example/users/b";
+        let follow = crate::activitypub::activity::follow(
+            &format!("{actor_id}#follows/2"),
+            actor_id,
+            "https://example.com/users/a",
+        );
+        create("a", actor_id, &follow, settings).await.unwrap();
+
+        // Act
+        let other = withdraw("a", actor_id, &format!("{actor_id}#follows/1"), settings).await;
+        let pending = list("a", settings).await.unwrap();
+        let actual = withdraw("a", actor_id, &format!("{actor_id}#follows/2"), settings).await;
+
+        // Assert
+        assert_eq!(other, Ok(false));
+        assert_eq!(pending.len(), 1);
+        assert_eq!(actual, Ok(true));
+        assert!(list("a", settings).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_approve_retry() {
+        // Arrange
+        let settings = Settings::in_memory();
+        let settings = <&rocket::State<Settings>>::from(&settings);
+        // nothing listens on the discard port, so the inbox can't be fetched at first
+        let actor_id = "http://127.0.0.1:9/users/b";
+        let follow = crate::activitypub::activity::follow(
+            &format!("{actor_id}#follows/1"),
+            actor_id,
+            "https://example.com/users/a",
+        );
+        create("a", actor_id, &follow, settings).await.unwrap();
+        let failed = approve("a", actor_id, settings).await;
+        let actor = serde_json::json!({ "id": actor_id, "inbox": format!("{actor_id}/inbox") });
+        crate::store::put(
+            settings.store.as_ref(),
+            "actor/127.0.0.1:9",
+            actor_id,
+            &actor,
+        )
+        .await
+        .unwrap();
+
+        // Act
+        approve("a", actor_id, settings).await.unwrap();
+
+        // Assert
+        assert!(failed.is_err());
+        assert!(list("a", settings).await.unwrap().is_empty());
+        assert!(super::super::follower::get("a", actor_id, settings)
+            .await
+            .unwrap()
+            .is_some());
+        let due = super::super::delivery::get_due(settings.store.as_ref(), u64::MAX - 1, 10)
+            .await
+            .unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].inbox, format!("{actor_id}/inbox"));
+    }
+}
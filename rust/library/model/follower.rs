@@ -0,0 +1,176 @@
+use crate::activitypub::collection::{Cursor, Page};
+use crate::model::delivery::Delivery;
+use crate::settings::Settings;
+use crate::store::Store;
+use crate::Error;
+use time::OffsetDateTime;
+
+/// # Errors
+///
+/// Will return `Err` if it can´t insert the follower.
+pub async fn create(
+    username: &str,
+    actor_id: &str,
+    follow: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    let partition = format!("users/{username}/followers");
+    crate::store::put(
+        settings.store.as_ref(),
+        partition.as_str(),
+        actor_id,
+        follow,
+    )
+    .await?;
+    Ok(())
+}
+
+/// # Errors
+///
+/// Will return `Err` if it can´t get the follower.
+pub async fn get(
+    username: &str,
+    actor_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<Option<serde_json::Value>> {
+    let partition = format!("users/{username}/followers");
+    crate::store::get(
+        settings.store.as_ref(),
+        partition.as_str(),
+        actor_id,
+        &["id", "actor"],
+    )
+    .await
+}
+
+/// # Errors
+///
+/// Will return `Err` if it can´t delete the follower.
+pub async fn delete(
+    username: &str,
+    actor_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    let partition = format!("users/{username}/followers");
+    settings
+        .store
+        .delete_item(partition.as_str(), actor_id)
+        .await?;
+    Ok(())
+}
+
+/// # Errors
+///
+/// Will return `Err` if it can´t count the followers.
+pub async fn count(username: &str, settings: &rocket::State<Settings>) -> crate::Result<i32> {
+    let partition = format!("users/{username}/followers");
+    settings.store.count(partition.as_str(), None).await
+}
+
+/// Gets a page of follower actor ids.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t query the followers.
+pub async fn get_page(
+    username: &str,
+    cursor: Cursor<'_>,
+    page_size: usize,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<Page<String>> {
+    load_page(username, cursor, page_size, settings.store.as_ref()).await
+}
+
+/// Like [`get_page`], straight from a store for callers without [`Settings`].
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t query the followers.
+pub async fn load_page(
+    username: &str,
+    cursor: Cursor<'_>,
+    page_size: usize,
+    store: &dyn Store,
+) -> crate::Result<Page<String>> {
+    let partition = format!("users/{username}/followers");
+    let page = store
+        .get_page(partition.as_str(), None, cursor, page_size)
+        .await?;
+    Ok(Page {
+        items: page
+            .items
+            .iter()
+            .filter_map(crate::store::get_sort_value)
+            .collect(),
+        next: page.next,
+        prev: page.prev,
+    })
+}
+
+/// Queues an `Accept` of `follow` for the inbox of the follower.
+///
+/// # Errors
+///
+/// Will return `Err` if the follower inbox can´t be found or the delivery can´t be queued.
+pub async fn accept(
+    username: &str,
+    follow: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    respond(
+        crate::activitypub::activity::accept,
+        "accepts",
+        username,
+        follow,
+        settings,
+    )
+    .await
+}
+
+/// Queues a `Reject` of `follow` for the inbox of the follower.
+///
+/// # Errors
+///
+/// Will return `Err` if the follower inbox can´t be found or the delivery can´t be queued.
+pub async fn reject(
+    username: &str,
+    follow: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    respond(
+        crate::activitypub::activity::reject,
+        "rejects",
+        username,
+        follow,
+        settings,
+    )
+    .await
+}
+
+async fn respond(
+    build: fn(&str, &str, &serde_json::Value) -> serde_json::Value,
+    collection: &str,
+    username: &str,
+    follow: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    let follower_id = follow["actor"]
+        .as_str()
+        .ok_or_else(|| Error::Parse("Missing actor id for follow".to_owned()))?;
+    let inbox = super::actor::get_inbox(follower_id, settings).await?;
+    let user_uri = format!("{}/users/{username}", settings.base_url);
+    let id = format!(
+        "{user_uri}#{collection}/follows/{}",
+        crate::faas_snowflake_id::get_id(settings.node_id)
+    );
+    let delivery = Delivery::new(
+        username,
+        &id,
+        &inbox,
+        build(&id, &user_uri, follow).to_string(),
+        format!("{user_uri}#main-key"),
+        OffsetDateTime::now_utc().unix_timestamp().unsigned_abs(),
+    );
+    super::delivery::enqueue(settings.store.as_ref(), &delivery).await?;
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use crate::activitypub::activity;
+use crate::activitypub::collection::{Cursor, Page};
+use crate::model::delivery::Delivery;
+use crate::settings::Settings;
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// A `Follow` sent by a local user, pending until the followed actor accepts it.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Following {
+    pub accepted: bool,
+    pub id: String,
+}
+
+/// Follows the remote account behind `handle` on behalf of the local user.
+///
+/// The `Follow` goes through the delivery queue, which retries it until the account answers.
+///
+/// # Errors
+///
+/// Will return `Err` if the handle can´t be resolved or the `Follow` can´t be queued.
+pub async fn follow(
+    username: &str,
+    handle: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<String> {
+    super::user::get_existing(username, settings).await?;
+    let actor_id = crate::activitypub::webfinger::resolve(handle).await?;
+    let inbox = super::actor::get_inbox(&actor_id, settings).await?;
+    let user_uri = format!("{}/users/{username}", settings.base_url);
+    let id = format!(
+        "{user_uri}#follows/{}",
+        crate::faas_snowflake_id::get_id(settings.node_id)
+    );
+    put(username, &actor_id, &id, false, settings).await?;
+    let delivery = Delivery::new(
+        username,
+        &id,
+        &inbox,
+        activity::follow(&id, &user_uri, &actor_id).to_string(),
+        format!("{user_uri}#main-key"),
+        OffsetDateTime::now_utc().unix_timestamp().unsigned_abs(),
+    );
+    super::delivery::enqueue(settings.store.as_ref(), &delivery).await?;
+    Ok(actor_id)
+}
+
+/// Counts the accounts that accepted to be followed by the user.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t count the followings.
+pub async fn count(username: &str, settings: &rocket::State<Settings>) -> crate::Result<i32> {
+    let partition = format!("users/{username}/following");
+    settings
+        .store
+        .count(partition.as_str(), Some("accepted"))
+        .await
+}
+
+/// Gets a page of the actor ids that accepted to be followed by the user.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t query the followings.
+pub async fn get_page(
+    username: &str,
+    cursor: Cursor<'_>,
+    page_size: usize,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<Page<String>> {
+    let partition = format!("users/{username}/following");
+    let page = settings
+        .store
+        .get_page(partition.as_str(), Some("accepted"), cursor, page_size)
+        .await?;
+    Ok(Page {
+        items: page
+            .items
+            .iter()
+            .filter_map(crate::store::get_sort_value)
+            .collect(),
+        next: page.next,
+        prev: page.prev,
+    })
+}
+
+/// # Errors
+///
+/// Will return `Err` if it can´t get the following.
+pub async fn get(
+    username: &str,
+    actor_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<Option<Following>> {
+    let partition = format!("users/{username}/following");
+    crate::store::get(
+        settings.store.as_ref(),
+        partition.as_str(),
+        actor_id,
+        &["accepted", "id"],
+    )
+    .await
+}
+
+/// Marks the `Follow` with id `follow_id` as accepted by `actor_id`.
+///
+/// # Errors
+///
+/// Will return `Err` if the user didn't send such a `Follow` to that actor.
+pub async fn accept(
+    username: &str,
+    actor_id: &str,
+    follow_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    let following = get_pending(username, actor_id, follow_id, settings).await?;
+    put(username, actor_id, &following.id, true, settings).await?;
+    Ok(())
+}
+
+/// Forgets the `Follow` with id `follow_id` rejected by `actor_id`.
+///
+/// # Errors
+///
+/// Will return `Err` if the user didn't send such a `Follow` to that actor
+/// or it can´t delete the following.
+pub async fn reject(
+    username: &str,
+    actor_id: &str,
+    follow_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    get_pending(username, actor_id, follow_id, settings).await?;
+    let partition = format!("users/{username}/following");
+    settings
+        .store
+        .delete_item(partition.as_str(), actor_id)
+        .await?;
+    Ok(())
+}
+
+async fn get_pending(
+    username: &str,
+    actor_id: &str,
+    follow_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<Following> {
+    let following = get(username, actor_id, settings)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("{username} doesn't follow {actor_id}")))?;
+    if following.id != follow_id {
+        return Err(Error::Invalid(format!(
+            "{follow_id} isn't a follow of {actor_id}"
+        )));
+    }
+
+    Ok(following)
+}
+
+async fn put(
+    username: &str,
+    actor_id: &str,
+    follow_id: &str,
+    accepted: bool,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    let user_uri = format!("{}/users/{username}", settings.base_url);
+    let mut item = activity::follow(follow_id, &user_uri, actor_id);
+    item["accepted"] = serde_json::Value::Bool(accepted);
+    let partition = format!("users/{username}/following");
+    crate::store::put(settings.store.as_ref(), partition.as_str(), actor_id, &item).await?;
+    Ok(())
+}
@@ -0,0 +1,274 @@
+use crate::settings::Settings;
+use crate::Error;
+use serde_json::Value;
+use tracing::{event, Level};
+
+/// Stores the object of a `Create` or an `Update` by `actor_id`, replacing the previous version.
+///
+/// Only the actor the object is attributed to can store it, and only over its own objects.
+///
+/// # Errors
+///
+/// Will return `Err` if the object isn't attributed to the actor, replaces an object of someone
+/// else or can´t be saved.
+pub async fn put_object(
+    username: &str,
+    actor_id: &str,
+    object_id: &str,
+    object: &Value,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    if !is_author(object, actor_id) {
+        return Err(Error::Invalid(format!(
+            "{object_id} isn't attributed to {actor_id}"
+        )));
+    }
+
+    let partition = format!("users/{username}/inbox");
+    check_author(&partition, object_id, actor_id, settings).await?;
+    crate::store::put(settings.store.as_ref(), &partition, object_id, object).await
+}
+
+/// Leaves a `Tombstone` in place of an object that `actor_id` deleted.
+///
+/// # Errors
+///
+/// Will return `Err` if the object belongs to someone else or the tombstone can´t be saved.
+pub async fn delete_object(
+    username: &str,
+    actor_id: &str,
+    object_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    let partition = format!("users/{username}/inbox");
+    if !check_author(&partition, object_id, actor_id, settings).await? {
+        event!(Level::DEBUG, "{username} never received {object_id}");
+        return Ok(());
+    }
+
+    // the author stays, so that nobody else can take the id over
+    let tombstone = serde_json::json!({
+        "id": object_id,
+        "type": "Tombstone",
+        "attributedTo": actor_id,
+    });
+    crate::store::put(settings.store.as_ref(), &partition, object_id, &tombstone).await
+}
+
+/// Stores an activity like a `Like` or an `Announce` in a collection of the user, by its id.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t save the activity.
+pub async fn put_activity(
+    username: &str,
+    collection: &str,
+    activity_id: &str,
+    activity: &Value,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    let partition = format!("users/{username}/{collection}");
+    crate::store::put(settings.store.as_ref(), &partition, activity_id, activity).await
+}
+
+/// Removes an activity stored by [`put_activity`] that `actor_id` takes back with an `Undo`,
+/// returning whether there was one.
+///
+/// # Errors
+///
+/// Will return `Err` if the activity was sent by someone else or it can´t be deleted.
+pub async fn delete_activity(
+    username: &str,
+    collection: &str,
+    actor_id: &str,
+    activity_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<bool> {
+    let partition = format!("users/{username}/{collection}");
+    if !check_author(&partition, activity_id, actor_id, settings).await? {
+        return Ok(false);
+    }
+
+    settings.store.delete_item(&partition, activity_id).await?;
+    Ok(true)
+}
+
+/// Whether there is an item with the id, failing if it was written by someone other than
+/// `actor_id`.
+async fn check_author(
+    partition: &str,
+    id: &str,
+    actor_id: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<bool> {
+    let existing: Option<Value> = crate::store::get(
+        settings.store.as_ref(),
+        partition,
+        id,
+        &["attributedTo", "actor"],
+    )
+    .await?;
+    match existing {
+        Some(existing) if !is_author(&existing, actor_id) => {
+            Err(Error::Invalid(format!("{id} doesn't belong to {actor_id}")))
+        }
+        existing => Ok(existing.is_some()),
+    }
+}
+
+/// Whether the object is attributed to the actor, or is an activity the actor sent.
+fn is_author(object: &Value, actor_id: &str) -> bool {
+    let is_actor =
+        |value: &Value| value.as_str().or_else(|| value["id"].as_str()) == Some(actor_id);
+    match &object["attributedTo"] {
+        Value::Null => object["actor"].as_str() == Some(actor_id),
+        Value::Array(authors) => authors.iter().any(is_actor),
+        author => is_actor(author),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE_ID: &str = "https://remote.example/notes/1";
+
+    fn note(attributed_to: &str, content: &str) -> Value {
+        serde_json::json!({
+            "id": NOTE_ID,
+            "type": "Note",
+            "attributedTo": attributed_to,
+            "content": content,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_put_object_not_attributed() {
+        // Arrange
+        let settings = Settings::in_memory();
+        let settings = <&rocket::State<Settings>>::from(&settings);
+        let object = note("https://remote.example/users/b", "hi");
+
+        // Act
+        let actual = put_object(
+            "a",
+            "https://remote.example/users/c",
+            NOTE_ID,
+            &object,
+            settings,
+        )
+        .await;
+
+        // Assert
+        assert!(matches!(actual, Err(Error::Invalid(_))));
+    }
+
+    #[tokio::test]
+    async fn test_put_object_of_someone_else() {
+        // Arrange
+        let settings = Settings::in_memory();
+        let settings = <&rocket::State<Settings>>::from(&settings);
+        let author = "https://remote.example/users/b";
+        put_object("a", author, NOTE_ID, &note(author, "hi"), settings)
+            .await
+            .unwrap();
+        let other = "https://remote.example/users/c";
+
+        // Act
+        let updated = put_object("a", other, NOTE_ID, &note(other, "bye"), settings).await;
+        let deleted = delete_object("a", other, NOTE_ID, settings).await;
+
+        // Assert
+        assert!(matches!(updated, Err(Error::Invalid(_))));
+        assert!(matches!(deleted, Err(Error::Invalid(_))));
+        let stored: Value = crate::store::get(
+            settings.store.as_ref(),
+            "users/a/inbox",
+            NOTE_ID,
+            &["content"],
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(stored["content"], "hi");
+    }
+
+    #[tokio::test]
+    async fn test_delete_object() {
+        // Arrange
+        let settings = Settings::in_memory();
+        let settings = <&rocket::State<Settings>>::from(&settings);
+        let author = "https://remote.example/users/b";
+        put_object("a", author, NOTE_ID, &note(author, "hi"), settings)
+            .await
+            .unwrap();
+
+        // Act
+        delete_object("a", author, NOTE_ID, settings).await.unwrap();
+        let recreated = put_object(
+            "a",
+            "https://remote.example/users/c",
+            NOTE_ID,
+            &note("https://remote.example/users/c", "mine"),
+            settings,
+        )
+        .await;
+
+        // Assert
+        let stored: Value =
+            crate::store::get(settings.store.as_ref(), "users/a/inbox", NOTE_ID, &["type"])
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(stored["type"], "Tombstone");
+        assert!(matches!(recreated, Err(Error::Invalid(_))));
+    }
+
+    #[tokio::test]
+    async fn test_delete_activity() {
+        // Arrange
+        let settings = Settings::in_memory();
+        let settings = <&rocket::State<Settings>>::from(&settings);
+        let activity_id = "https://remote.example/users/b#likes/1";
+        let like = serde_json::json!({
+            "id": activity_id,
+            "type": "Like",
+            "actor": "https://remote.example/users/b",
+            "object": "https://example.com/users/a/statuses/1",
+        });
+        put_activity("a", "likes", activity_id, &like, settings)
+            .await
+            .unwrap();
+
+        // Act
+        let by_other = delete_activity(
+            "a",
+            "likes",
+            "https://remote.example/users/c",
+            activity_id,
+            settings,
+        )
+        .await;
+        let by_actor = delete_activity(
+            "a",
+            "likes",
+            "https://remote.example/users/b",
+            activity_id,
+            settings,
+        )
+        .await;
+        let again = delete_activity(
+            "a",
+            "likes",
+            "https://remote.example/users/b",
+            activity_id,
+            settings,
+        )
+        .await;
+
+        // Assert
+        assert!(matches!(by_other, Err(Error::Invalid(_))));
+        assert_eq!(by_actor, Ok(true));
+        assert_eq!(again, Ok(false));
+    }
+}
@@ -1,2 +1,8 @@
 pub mod actor;
+pub mod delivery;
+pub mod follow_request;
+pub mod follower;
+pub mod following;
+pub mod inbox;
+pub mod status;
 pub mod user;
@@ -0,0 +1,281 @@
+use crate::activitypub::collection::Page;
+use crate::activitypub::object::Object;
+use crate::settings::Settings;
+use crate::store::SortKeyCondition;
+
+/// The smallest snowflake id with 19 digits, generated in 1977.
+const MIN_ID: u64 = 10_u64.pow(18);
+
+/// The largest snowflake id with 19 digits, generated in 2045.
+const MAX_ID: u64 = 10_u64.pow(19) - 1;
+
+/// Snowflake id bounds of an outbox page, as in the Mastodon API.
+///
+/// `max_id` and `since_id` return the newest statuses below and above the given ids,
+/// while `min_id` returns the statuses right after it.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Range {
+    pub max_id: Option<u64>,
+    pub min_id: Option<u64>,
+    pub since_id: Option<u64>,
+}
+
+impl Range {
+    fn lower(self) -> Option<u64> {
+        self.min_id.max(self.since_id)
+    }
+
+    const fn is_ascending(self) -> bool {
+        self.min_id.is_some()
+    }
+
+    /// The sort key condition excluding both bounds, if the range isn't empty.
+    ///
+    /// Sort keys are compared as strings, so both bounds are clamped to the snowflake ids
+    /// between [`MIN_ID`] and [`MAX_ID`], which all have the same number of digits.
+    fn condition(self) -> Option<(String, String)> {
+        let low = self
+            .lower()
+            .map_or(Some(MIN_ID), |low| low.checked_add(1))?
+            .max(MIN_ID);
+        let high = self
+            .max_id
+            .map_or(Some(MAX_ID), |high| high.checked_sub(1))?
+            .min(MAX_ID);
+        (low <= high).then(|| (low.to_string(), high.to_string()))
+    }
+}
+
+/// Counts the statuses of the user.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t count the statuses.
+pub async fn count(username: &str, settings: &rocket::State<Settings>) -> crate::Result<i32> {
+    let partition = format!("users/{username}/statuses");
+    settings.store.count(partition.as_str(), None).await
+}
+
+/// Gets a page of the statuses of the user within `range`, newest first.
+///
+/// `next` is the id of the oldest status when there are older ones, and `prev` the id of the
+/// newest when there are newer ones.
+///
+/// # Errors
+///
+/// Will return `Err` if it can´t query the statuses.
+pub async fn get_page(
+    username: &str,
+    range: Range,
+    page_size: usize,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<Page<Object>> {
+    let Some((low, high)) = range.condition() else {
+        return Ok(Page {
+            items: Vec::new(),
+            next: None,
+            prev: None,
+        });
+    };
+    let partition = format!("users/{username}/statuses");
+    let ascending = range.is_ascending();
+    let mut items = settings
+        .store
+        .get_range(
+            partition.as_str(),
+            SortKeyCondition::Between(&low, &high),
+            ascending,
+            page_size + 1,
+        )
+        .await?;
+    let has_more = items.len() > page_size;
+    items.truncate(page_size);
+    if ascending {
+        items.reverse();
+    }
+    let ids = items
+        .iter()
+        .filter_map(crate::store::get_sort_value)
+        .collect::<Vec<String>>();
+    let (Some(newest), Some(oldest)) = (ids.first(), ids.last()) else {
+        return Ok(Page {
+            items: Vec::new(),
+            next: None,
+            prev: None,
+        });
+    };
+    let has_older = if ascending {
+        exists(&partition, SortKeyCondition::LessThan(oldest), settings).await?
+    } else {
+        has_more
+    };
+    let has_newer = if ascending {
+        has_more
+    } else if range.max_id.is_some() {
+        exists(&partition, SortKeyCondition::GreaterThan(newest), settings).await?
+    } else {
+        false
+    };
+    Ok(Page {
+        next: has_older.then(|| oldest.clone()),
+        prev: has_newer.then(|| newest.clone()),
+        items: crate::store::from_items(items)?,
+    })
+}
+
+async fn exists(
+    partition: &str,
+    condition: SortKeyCondition<'_>,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<bool> {
+    Ok(!settings
+        .store
+        .get_range(partition, condition, false, 1)
+        .await?
+        .is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_condition() {
+        assert_eq!(
+            Range::default().condition(),
+            Some((
+                "1000000000000000000".to_owned(),
+                "9999999999999999999".to_owned()
+            ))
+        );
+        assert_eq!(
+            Range {
+                max_id: Some(7_049_093_257_877_979_136),
+                min_id: None,
+                since_id: Some(7_049_093_257_877_979_000),
+            }
+            .condition(),
+            Some((
+                "7049093257877979001".to_owned(),
+                "7049093257877979135".to_owned()
+            ))
+        );
+        assert_eq!(
+            Range {
+                max_id: Some(5),
+                min_id: Some(4),
+                since_id: None,
+            }
+            .condition(),
+            None
+        );
+        assert_eq!(
+            Range {
+                max_id: None,
+                min_id: None,
+                since_id: Some(5),
+            }
+            .condition(),
+            Some((
+                "1000000000000000000".to_owned(),
+                "9999999999999999999".to_owned()
+            ))
+        );
+        assert_eq!(
+            Range {
+                max_id: Some(5),
+                min_id: None,
+                since_id: None,
+            }
+            .condition(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_page() {
+        // Arrange
+        let settings = Settings::in_memory();
+        for id in 1_000_000_000_000_000_001_u64..=1_000_000_000_000_000_005 {
+            let status = serde_json::json!({ "id": id.to_string() });
+            crate::store::put(
+                settings.store.as_ref(),
+                "users/a/statuses",
+                &id.to_string(),
+                &status,
+            )
+            .await
+            .unwrap();
+        }
+        let settings = <&rocket::State<Settings>>::from(&settings);
+
+        // Act
+        let actual = get_page(
+            "a",
+            Range {
+                max_id: Some(1_000_000_000_000_000_005),
+                min_id: None,
+                since_id: None,
+            },
+            2,
+            settings,
+        )
+        .await
+        .unwrap();
+
+        // Assert
+        assert_eq!(
+            actual
+                .items
+                .iter()
+                .filter_map(|status| status.id.as_deref())
+                .collect::<Vec<_>>(),
+            ["1000000000000000004", "1000000000000000003"]
+        );
+        assert_eq!(actual.next.as_deref(), Some("1000000000000000003"));
+        assert_eq!(actual.prev.as_deref(), Some("1000000000000000004"));
+    }
+
+    #[tokio::test]
+    async fn test_get_page_short_ids() {
+        // Arrange
+        let settings = Settings::in_memory();
+        for id in 1_000_000_000_000_000_001_u64..=1_000_000_000_000_000_002 {
+            let status = serde_json::json!({ "id": id.to_string() });
+            crate::store::put(
+                settings.store.as_ref(),
+                "users/a/statuses",
+                &id.to_string(),
+                &status,
+            )
+            .await
+            .unwrap();
+        }
+        let settings = <&rocket::State<Settings>>::from(&settings);
+        let since = Range {
+            max_id: None,
+            min_id: None,
+            since_id: Some(5),
+        };
+        let max = Range {
+            max_id: Some(5),
+            min_id: None,
+            since_id: None,
+        };
+
+        // Act
+        let since = get_page("a", since, 20, settings).await.unwrap();
+        let max = get_page("a", max, 20, settings).await.unwrap();
+
+        // Assert
+        assert_eq!(
+            since
+                .items
+                .iter()
+                .filter_map(|status| status.id.as_deref())
+                .collect::<Vec<_>>(),
+            ["1000000000000000002", "1000000000000000001"]
+        );
+        assert!(max.items.is_empty());
+    }
+}
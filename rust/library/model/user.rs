@@ -1,5 +1,10 @@
 // copied from https://github.com/Plume-org/Plume/blob/main/plume-models/src/users.rs
+use crate::activitypub::multikey;
+use crate::activitypub::object::{Multikey, Object, PublicKey};
+use crate::model::delivery::FanOut;
 use crate::settings::Settings;
+use crate::store::Store;
+use crate::Error;
 use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -11,12 +16,34 @@ use openssl::{
 use serde::{Deserialize, Serialize};
 
 const KEYSIZE: u32 = 4096;
+const ED25519_KEY_FRAGMENT: &str = "#ed25519-key";
+/// How long the keys replaced by [`rotate_keys`] still verify signatures, which is as long
+/// as the signatures made with them before the rotation are recent enough to be accepted.
+const PREVIOUS_KEYS_SECONDS: u64 = 12 * 60 * 60;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct User {
+    /// PKCS#8 DER of the Ed25519 key, which users created before it was supported don't have.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "serde_bytes")]
+    pub ed25519_private_key: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "serde_bytes")]
+    pub ed25519_public_key: Option<Vec<u8>>,
+    #[serde(default)]
+    pub hide_followers: bool,
+    #[serde(default)]
+    pub manually_approves_followers: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "preferredUsername")]
     pub preferred_username: Option<String>,
+    /// Keys replaced by the last rotation, at `rotated_unix_time_seconds`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "serde_bytes")]
+    pub previous_ed25519_public_key: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "serde_bytes")]
+    pub previous_public_key: Option<Vec<u8>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(with = "serde_bytes")]
     pub private_key: Option<Vec<u8>>,
@@ -24,6 +51,8 @@ pub struct User {
     #[serde(with = "serde_bytes")]
     pub public_key: Option<Vec<u8>>,
     pub published_unix_time_seconds: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotated_unix_time_seconds: Option<u64>,
 }
 
 impl User {
@@ -35,76 +64,413 @@ impl User {
 
         published_time.format(&Rfc3339).unwrap()
     }
+
+    /// The private key with the id `key_id`, the Ed25519 one for the id given by
+    /// [`ed25519_key_id`] and the RSA one for any other.
+    #[must_use]
+    pub fn get_private_key(&self, key_id: &str) -> Option<&[u8]> {
+        if key_id.ends_with(ED25519_KEY_FRAGMENT) {
+            self.ed25519_private_key.as_deref()
+        } else {
+            self.private_key.as_deref()
+        }
+    }
 }
 
-/// # Panics
+/// The id of the key to sign requests with, the Ed25519 key of the user with the RSA
+/// `main_key_id` when `ed25519` and the RSA key otherwise.
+#[must_use]
+pub fn get_signature_key_id(main_key_id: &str, ed25519: bool) -> String {
+    match main_key_id.split_once('#') {
+        Some((user_uri, _)) if ed25519 => ed25519_key_id(user_uri),
+        _ => main_key_id.to_owned(),
+    }
+}
+
+/// The id of the user's Ed25519 key, published as a FEP-521a Multikey, next to the RSA
+/// `{user_uri}#main-key` that every server supports.
+#[must_use]
+pub fn ed25519_key_id(user_uri: &str) -> String {
+    format!("{user_uri}{ED25519_KEY_FRAGMENT}")
+}
+
+/// Gets the user straight from a store, for callers without [`Settings`].
 ///
-/// Will panic if it can´t get the user.
-pub async fn get_item(
-    username: &str,
-    db_client: &aws_sdk_dynamodb::Client,
-    table_name: &str,
-) -> aws_sdk_dynamodb::operation::get_item::GetItemOutput {
+/// # Errors
+///
+/// Will return `Err` if it can´t get the user.
+pub async fn load(username: &str, store: &dyn Store) -> crate::Result<Option<User>> {
     let partition = format!("users/{username}");
-    crate::dynamodb::get_item(
-        db_client,
-        table_name,
+    crate::store::get(
+        store,
         partition.as_str(),
         "user",
-        "private_key, public_key, published_unix_time_seconds",
+        &[
+            "ed25519_private_key",
+            "ed25519_public_key",
+            "hide_followers",
+            "manually_approves_followers",
+            "preferredUsername",
+            "previous_ed25519_public_key",
+            "previous_public_key",
+            "private_key",
+            "public_key",
+            "published_unix_time_seconds",
+            "rotated_unix_time_seconds",
+        ],
     )
     .await
-    .unwrap()
 }
 
-/// # Panics
+/// # Errors
 ///
-/// Will panic if it can´t get the user.
-pub async fn get(username: &str, settings: &rocket::State<Settings>) -> Option<User> {
-    let get_item_output = get_item(username, &settings.db_client, &settings.table_name).await;
-    if let Some(item) = get_item_output.item {
-        let user: User = serde_dynamo::from_item(item).unwrap();
-        return Some(user);
-    }
+/// Will return `Err` if it can´t get the user.
+pub async fn get(
+    username: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<Option<User>> {
+    load(username, settings.store.as_ref()).await
+}
+
+/// Like [`get`], for when the user must exist.
+///
+/// # Errors
+///
+/// Will return `Err` if the user is not found or it can´t be read.
+pub async fn get_existing(
+    username: &str,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<User> {
+    get(username, settings)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("user {username}")))
+}
 
-    None
+/// Locks or unlocks the account, so that new followers need to be approved.
+///
+/// # Errors
+///
+/// Returns an error if the user is not found or can´t be saved.
+pub async fn set_manually_approves_followers(
+    username: &str,
+    manually_approves_followers: bool,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    update(username, settings, |user| {
+        user.manually_approves_followers = manually_approves_followers;
+    })
+    .await
+}
+
+/// Hides or shows the list of followers, their count is always public.
+///
+/// # Errors
+///
+/// Returns an error if the user is not found or can´t be saved.
+pub async fn set_hide_followers(
+    username: &str,
+    hide_followers: bool,
+    settings: &rocket::State<Settings>,
+) -> crate::Result<()> {
+    update(username, settings, |user| {
+        user.hide_followers = hide_followers;
+    })
+    .await
+}
+
+async fn update(
+    username: &str,
+    settings: &rocket::State<Settings>,
+    change: impl FnOnce(&mut User) + Send,
+) -> crate::Result<()> {
+    let mut user = get_existing(username, settings).await?;
+    change(&mut user);
+    let partition = format!("users/{username}");
+    crate::store::put(settings.store.as_ref(), partition.as_str(), "user", &user).await?;
+    Ok(())
 }
 
+/// Gets the public key with the id `key_id`, as [`User::get_private_key`] picks it,
+/// followed by the one it replaced while that one still verifies signatures.
+///
 /// # Errors
 ///
-/// Returns an error if the user is not found.
-pub async fn get_public_key(
+/// Returns an error if the user or the key is not found or the key is invalid.
+///
+/// # Panics
+///
+/// Will panic if the system clock is before the Unix epoch.
+pub async fn get_public_keys(
     username: &str,
+    key_id: &str,
     settings: &rocket::State<Settings>,
-) -> Result<PKey<Public>, String> {
-    if let Some(user) = get(username, settings).await {
-        let public_key = user.public_key.unwrap();
-        let rsa = Rsa::public_key_from_der(&public_key).unwrap();
-        return PKey::from_rsa(rsa).map_err(|e| format!("Failed to from_rsa {e:?}"));
+) -> crate::Result<Vec<PKey<Public>>> {
+    let user = get_existing(username, settings).await?;
+    let is_previous_valid = is_previous_valid(&user);
+    if key_id.ends_with(ED25519_KEY_FRAGMENT) {
+        let public_key = user
+            .ed25519_public_key
+            .ok_or_else(|| Error::NotFound(format!("Ed25519 key of {username}")))?;
+        let mut public_keys = vec![PKey::public_key_from_der(&public_key)?];
+        if let Some(previous) = user
+            .previous_ed25519_public_key
+            .filter(|_| is_previous_valid)
+        {
+            public_keys.push(PKey::public_key_from_der(&previous)?);
+        }
+        return Ok(public_keys);
     }
 
-    Err("User not found".to_owned())
+    let public_key = user
+        .public_key
+        .ok_or_else(|| Error::NotFound(format!("public key of {username}")))?;
+    let mut public_keys = vec![PKey::from_rsa(Rsa::public_key_from_der(&public_key)?)?];
+    if let Some(previous) = user.previous_public_key.filter(|_| is_previous_valid) {
+        public_keys.push(PKey::from_rsa(Rsa::public_key_from_der(&previous)?)?);
+    }
+    Ok(public_keys)
+}
+
+/// Whether the keys replaced by the last rotation still verify signatures.
+///
+/// # Panics
+///
+/// Will panic if the system clock is before the Unix epoch.
+fn is_previous_valid(user: &User) -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    user.rotated_unix_time_seconds
+        .is_some_and(|rotated| now < rotated + PREVIOUS_KEYS_SECONDS)
+}
+
+/// The actor document of the user, with its public keys.
+///
+/// While the keys replaced by the last rotation still verify signatures, the previous Ed25519
+/// key follows the current one in `assertionMethod`, under the same id, so that servers that
+/// fetch the actor again can verify what was signed before. The `publicKey` only has room for
+/// the current RSA key.
+///
+/// # Errors
+///
+/// Will return `Err` if the user has no public key or it's invalid.
+///
+/// # Panics
+///
+/// Will panic if the system clock is before the Unix epoch.
+pub fn get_actor(username: &str, user: &User, base_url: &str) -> crate::Result<Object> {
+    let public_key = user
+        .public_key
+        .as_deref()
+        .ok_or_else(|| Error::NotFound(format!("public key of {username}")))?;
+    let public_key = crate::rsa::der_to_pem(public_key)?;
+    let user_uri = format!("{base_url}/users/{username}");
+    let previous_ed25519_public_key = user
+        .previous_ed25519_public_key
+        .as_deref()
+        .filter(|_| is_previous_valid(user));
+    // users created before Ed25519 keys were supported only have the RSA key
+    let assertion_method = user
+        .ed25519_public_key
+        .as_deref()
+        .map(|ed25519_public_key| {
+            std::iter::once(ed25519_public_key)
+                .chain(previous_ed25519_public_key)
+                .map(|public_key| {
+                    Ok(Multikey {
+                        id: ed25519_key_id(&user_uri),
+                        r#type: "Multikey".to_owned(),
+                        controller: user_uri.clone(),
+                        public_key_multibase: multikey::der_to_multibase(public_key)?,
+                    })
+                })
+                .collect::<crate::Result<Vec<_>>>()
+        })
+        .transpose()?;
+    Ok(Object {
+        actor: None,
+        assertion_method,
+        atom_uri: None,
+        attachment: Some(Vec::new()),
+        attributed_to: None,
+        cc: None,
+        content: None,
+        context: crate::activitypub::context::actor(),
+        conversation: None,
+        devices: Some(format!("{user_uri}/collections/devices")),
+        discoverable: Some(false),
+        endpoints: None,
+        followers: Some(format!("{user_uri}/followers")),
+        following: Some(format!("{user_uri}/following")),
+        id: Some(user_uri.clone()),
+        in_reply_to: None,
+        in_reply_to_atom_uri: None,
+        inbox: Some(format!("{user_uri}/inbox")),
+        manually_approves_followers: Some(user.manually_approves_followers),
+        name: Some(username.to_owned()),
+        object: None,
+        outbox: Some(format!("{user_uri}/outbox")),
+        partition_key: None,
+        preferred_username: Some(username.to_owned()),
+        public_key: Some(PublicKey {
+            id: format!("{user_uri}#main-key"),
+            owner: user_uri,
+            public_key_pem: public_key,
+        }),
+        published: Some(user.get_published_time()),
+        r#type: Some("Person".to_owned()),
+        sensitive: None,
+        sort_key: None,
+        summary: Some(String::new()),
+        tag: Some(Vec::new()),
+        to: None,
+        url: Some(format!("{base_url}/@{username}")),
+        extra: serde_json::Value::Null,
+    })
+}
+
+/// Replaces the keys of the user with new ones and sends an `Update` of the actor to the
+/// followers.
+///
+/// The old public keys still verify the signatures made before for a while, and the `Update`
+/// makes the servers of the followers fetch the new keys.
+///
+/// # Errors
+///
+/// Will return `Err` if the user is not found, or it can´t generate the keys,
+/// save them or queue the `Update`.
+///
+/// # Panics
+///
+/// Will panic if the system clock is before the Unix epoch.
+pub async fn rotate_keys(username: &str, settings: &rocket::State<Settings>) -> crate::Result<()> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let keys = Keys::generate()?;
+    update(username, settings, |user| {
+        user.previous_public_key = user.public_key.replace(keys.rsa_public);
+        user.previous_ed25519_public_key = user.ed25519_public_key.replace(keys.ed25519_public);
+        user.private_key = Some(keys.rsa_private);
+        user.ed25519_private_key = Some(keys.ed25519_private);
+        user.rotated_unix_time_seconds = Some(now);
+    })
+    .await?;
+
+    let user = get_existing(username, settings).await?;
+    let actor = get_actor(username, &user, &settings.base_url)?;
+    let activity = crate::activitypub::activity::update_actor(&actor, now)?;
+    let fan_out = FanOut {
+        username: username.to_owned(),
+        activity_id: activity["id"].as_str().unwrap_or_default().to_owned(),
+        body: activity.to_string(),
+        signature_key_id: format!("{}/users/{username}#main-key", settings.base_url),
+        cursor: None,
+    };
+    super::delivery::put_fan_out(settings.store.as_ref(), &fan_out).await
 }
 
+/// A new key pair of each type, as DER.
+struct Keys {
+    rsa_private: Vec<u8>,
+    rsa_public: Vec<u8>,
+    ed25519_private: Vec<u8>,
+    ed25519_public: Vec<u8>,
+}
+
+impl Keys {
+    fn generate() -> crate::Result<Self> {
+        let keypair = Rsa::generate(KEYSIZE)?;
+        let ed25519_keypair = PKey::generate_ed25519()?;
+        Ok(Self {
+            rsa_private: keypair.private_key_to_der()?,
+            rsa_public: keypair.public_key_to_der()?,
+            ed25519_private: ed25519_keypair.private_key_to_der()?,
+            ed25519_public: ed25519_keypair.public_key_to_der()?,
+        })
+    }
+}
+
+/// # Errors
+///
+/// Will return `Err` if it can´t generate the key pair or save the user.
+///
 /// # Panics
 ///
-/// Will panic if it can´t generate the private key.
-pub async fn create(
-    db_client: &aws_sdk_dynamodb::Client,
-    table_name: &str,
-    preferred_username: &str,
-) -> User {
+/// Will panic if the system clock is before the Unix epoch.
+pub async fn create(store: &dyn Store, preferred_username: &str) -> crate::Result<User> {
     let since_unix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-    let keypair = Rsa::generate(KEYSIZE).unwrap();
+    let keys = Keys::generate()?;
     let partition = format!("users/{preferred_username}");
     let user = crate::model::user::User {
+        ed25519_private_key: Some(keys.ed25519_private),
+        ed25519_public_key: Some(keys.ed25519_public),
+        hide_followers: false,
+        manually_approves_followers: false,
         preferred_username: Some(preferred_username.to_owned()),
-        private_key: Some(keypair.private_key_to_der().unwrap()),
-        public_key: Some(keypair.public_key_to_der().unwrap()),
+        previous_ed25519_public_key: None,
+        previous_public_key: None,
+        private_key: Some(keys.rsa_private),
+        public_key: Some(keys.rsa_public),
         published_unix_time_seconds: since_unix.as_secs(),
+        rotated_unix_time_seconds: None,
     };
-    crate::dynamodb::put_item(db_client, table_name, partition.as_str(), "user", &user)
-        .await
-        .unwrap();
-    user
+    crate::store::put(store, partition.as_str(), "user", &user).await?;
+    Ok(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rotate_keys() {
+        // Arrange
+        let settings = Settings::in_memory();
+        let old_user = create(settings.store.as_ref(), "a").await.unwrap();
+        let settings = <&rocket::State<Settings>>::from(&settings);
+
+        // Act
+        rotate_keys("a", settings).await.unwrap();
+
+        // Assert
+        let user = get_existing("a", settings).await.unwrap();
+        assert_ne!(user.public_key, old_user.public_key);
+        assert_eq!(user.previous_public_key, old_user.public_key);
+        assert_eq!(
+            user.previous_ed25519_public_key,
+            old_user.ed25519_public_key
+        );
+        let public_keys = get_public_keys("a", "https://example.com/users/a#main-key", settings)
+            .await
+            .unwrap();
+        assert_eq!(public_keys.len(), 2);
+        assert_eq!(
+            public_keys[1].public_key_to_der().unwrap(),
+            PKey::from_rsa(
+                Rsa::public_key_from_der(old_user.public_key.as_deref().unwrap()).unwrap()
+            )
+            .unwrap()
+            .public_key_to_der()
+            .unwrap()
+        );
+        let fan_outs = crate::model::delivery::get_fan_outs(settings.store.as_ref(), 10)
+            .await
+            .unwrap();
+        let activity: serde_json::Value = serde_json::from_str(&fan_outs[0].body).unwrap();
+        assert_eq!(activity["type"], "Update");
+        let assertion_method = activity["object"]["assertionMethod"].as_array().unwrap();
+        assert_eq!(assertion_method.len(), 2);
+        assert_eq!(
+            assertion_method[1]["publicKeyMultibase"],
+            multikey::der_to_multibase(old_user.ed25519_public_key.as_deref().unwrap()).unwrap()
+        );
+        assert_eq!(assertion_method[1]["id"], assertion_method[0]["id"]);
+        assert_eq!(
+            activity["object"]["publicKey"]["publicKeyPem"],
+            crate::rsa::der_to_pem(user.public_key.as_deref().unwrap()).unwrap()
+        );
+    }
 }
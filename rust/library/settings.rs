@@ -1,7 +1,21 @@
 pub struct Settings {
     pub base_url: String,
-    pub db_client: aws_sdk_dynamodb::Client,
     pub domain_name: String,
     pub node_id: u64,
-    pub table_name: String,
+    pub store: Box<dyn crate::store::Store>,
+}
+
+#[cfg(test)]
+impl Settings {
+    /// The settings of `example.com` with an empty [`MemoryStore`](crate::store::MemoryStore),
+    /// for the tests.
+    #[must_use]
+    pub fn in_memory() -> Self {
+        Self {
+            base_url: "https://example.com".to_owned(),
+            domain_name: "example.com".to_owned(),
+            node_id: 1,
+            store: Box::new(crate::store::MemoryStore::new()),
+        }
+    }
 }
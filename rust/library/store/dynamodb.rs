@@ -0,0 +1,312 @@
+use super::{Item, SortKeyCondition, Store, PARTITION_KEY_NAME, SORT_KEY_NAME};
+use crate::activitypub::collection::{Cursor, Page};
+use aws_sdk_dynamodb::error::SdkError;
+use aws_sdk_dynamodb::operation::query::builders::QueryFluentBuilder;
+use aws_sdk_dynamodb::types::{AttributeValue, ReturnValue, Select};
+use aws_sdk_dynamodb::Client;
+use std::collections::HashMap;
+use tracing::{event, Level};
+
+/// A store backed by a single `DynamoDB` table.
+pub struct DynamoDbStore {
+    client: Client,
+    table_name: String,
+}
+
+impl DynamoDbStore {
+    #[must_use]
+    pub const fn new(client: Client, table_name: String) -> Self {
+        Self { client, table_name }
+    }
+
+    #[must_use]
+    pub const fn client(&self) -> &Client {
+        &self.client
+    }
+
+    #[must_use]
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    fn query_partition(&self, partition: &str, only_if: Option<&str>) -> QueryFluentBuilder {
+        let query = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("#partition_key = :valueToMatch")
+            .expression_attribute_names("#partition_key", PARTITION_KEY_NAME)
+            .expression_attribute_values(":valueToMatch", AttributeValue::S(partition.to_owned()));
+        match only_if {
+            Some(attribute_name) => query
+                .filter_expression("#only_if = :true")
+                .expression_attribute_names("#only_if", attribute_name)
+                .expression_attribute_values(":true", AttributeValue::Bool(true)),
+            None => query,
+        }
+    }
+}
+
+fn key(partition: &str, sort_value: &str) -> HashMap<String, AttributeValue> {
+    HashMap::from([
+        (
+            PARTITION_KEY_NAME.to_owned(),
+            AttributeValue::S(partition.to_owned()),
+        ),
+        (
+            SORT_KEY_NAME.to_owned(),
+            AttributeValue::S(sort_value.to_owned()),
+        ),
+    ])
+}
+
+#[rocket::async_trait]
+impl Store for DynamoDbStore {
+    async fn get_item(
+        &self,
+        partition: &str,
+        sort_value: &str,
+        attributes: &[&str],
+    ) -> crate::Result<Option<Item>> {
+        event!(
+            Level::DEBUG,
+            "Get item: table {} partition {partition} sort_value {sort_value} attributes {attributes:?}",
+            self.table_name
+        );
+        let mut get_item = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .set_key(Some(key(partition, sort_value)));
+        if !attributes.is_empty() {
+            // aliases avoid clashes with reserved words like type, object or to
+            let mut aliases = Vec::with_capacity(attributes.len());
+            for (index, attribute) in attributes.iter().enumerate() {
+                let alias = format!("#a{index}");
+                get_item = get_item.expression_attribute_names(&alias, *attribute);
+                aliases.push(alias);
+            }
+            get_item = get_item.projection_expression(aliases.join(", "));
+        }
+        let output = get_item.send().await?;
+        Ok(output.item.map(Item::from))
+    }
+
+    async fn put_item(&self, partition: &str, sort_value: &str, item: Item) -> crate::Result<()> {
+        let mut values: HashMap<String, AttributeValue> = item.into();
+        values.extend(key(partition, sort_value));
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(values))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn put_new_item(
+        &self,
+        partition: &str,
+        sort_value: &str,
+        item: Item,
+    ) -> crate::Result<bool> {
+        let mut values: HashMap<String, AttributeValue> = item.into();
+        values.extend(key(partition, sort_value));
+        let result = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(values))
+            .condition_expression("attribute_not_exists(#partition_key)")
+            .expression_attribute_names("#partition_key", PARTITION_KEY_NAME)
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(true),
+            Err(SdkError::ServiceError(err))
+                if err.err().is_conditional_check_failed_exception() =>
+            {
+                Ok(false)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn replace_item_if(
+        &self,
+        partition: &str,
+        sort_value: &str,
+        item: Item,
+        attribute: &str,
+        expected: Option<&super::AttributeValue>,
+    ) -> crate::Result<bool> {
+        let mut values: HashMap<String, AttributeValue> = item.into();
+        values.extend(key(partition, sort_value));
+        let builder = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(values))
+            .expression_attribute_names("#partition_key", PARTITION_KEY_NAME)
+            .expression_attribute_names("#attribute", attribute);
+        let builder = match expected {
+            Some(expected) => builder
+                .condition_expression("attribute_exists(#partition_key) AND #attribute = :expected")
+                .expression_attribute_values(":expected", expected.clone().into()),
+            None => builder.condition_expression(
+                "attribute_exists(#partition_key) AND attribute_not_exists(#attribute)",
+            ),
+        };
+        match builder.send().await {
+            Ok(_) => Ok(true),
+            Err(SdkError::ServiceError(err))
+                if err.err().is_conditional_check_failed_exception() =>
+            {
+                Ok(false)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn delete_item(&self, partition: &str, sort_value: &str) -> crate::Result<()> {
+        self.take_item(partition, sort_value).await?;
+        Ok(())
+    }
+
+    /// The old attributes returned by the deletion tell whether the item was there.
+    async fn take_item(&self, partition: &str, sort_value: &str) -> crate::Result<bool> {
+        event!(
+            Level::DEBUG,
+            "Delete item: table {} partition {partition} sort_value {sort_value}",
+            self.table_name
+        );
+        let output = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .set_key(Some(key(partition, sort_value)))
+            .return_values(ReturnValue::AllOld)
+            .send()
+            .await?;
+        Ok(output.attributes.is_some())
+    }
+
+    /// Follows `LastEvaluatedKey` past the 1 MB query limit.
+    async fn count(&self, partition: &str, only_if: Option<&str>) -> crate::Result<i32> {
+        let mut total = 0;
+        let mut exclusive_start_key = None;
+        loop {
+            let output = self
+                .query_partition(partition, only_if)
+                .select(Select::Count)
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await?;
+            total += output.count();
+            exclusive_start_key = output.last_evaluated_key;
+            if exclusive_start_key.is_none() {
+                return Ok(total);
+            }
+        }
+    }
+
+    async fn get_page(
+        &self,
+        partition: &str,
+        only_if: Option<&str>,
+        cursor: Cursor<'_>,
+        page_size: usize,
+    ) -> crate::Result<Page<Item>> {
+        event!(
+            Level::DEBUG,
+            "Get page: table {} partition {partition} cursor {cursor:?}",
+            self.table_name
+        );
+        let (exclusive_start_sort_value, scan_index_forward) = match cursor {
+            Cursor::First => (None, true),
+            Cursor::After(sort_value) => (Some(sort_value), true),
+            Cursor::Before(sort_value) => (Some(sort_value), false),
+        };
+        let mut exclusive_start_key =
+            exclusive_start_sort_value.map(|sort_value| key(partition, sort_value));
+        // one extra item tells whether there is another page
+        let mut items = Vec::new();
+        while items.len() <= page_size {
+            let remaining = page_size + 1 - items.len();
+            let output = self
+                .query_partition(partition, only_if)
+                .scan_index_forward(scan_index_forward)
+                .limit(i32::try_from(remaining).unwrap_or(i32::MAX))
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await?;
+            items.extend(output.items.unwrap_or_default().into_iter().map(Item::from));
+            exclusive_start_key = output.last_evaluated_key;
+            if exclusive_start_key.is_none() {
+                break;
+            }
+        }
+        Ok(super::to_page(items, cursor, page_size))
+    }
+
+    async fn get_range(
+        &self,
+        partition: &str,
+        condition: SortKeyCondition<'_>,
+        scan_index_forward: bool,
+        limit: usize,
+    ) -> crate::Result<Vec<Item>> {
+        event!(
+            Level::DEBUG,
+            "Get range: table {} partition {partition} condition {condition:?}",
+            self.table_name
+        );
+        let query = self
+            .query_partition(partition, None)
+            .scan_index_forward(scan_index_forward);
+        let query = match condition {
+            SortKeyCondition::Any => query,
+            SortKeyCondition::LessThan(sort_value) => query
+                .key_condition_expression(
+                    "#partition_key = :valueToMatch AND #sort_key < :sortValue",
+                )
+                .expression_attribute_names("#sort_key", SORT_KEY_NAME)
+                .expression_attribute_values(
+                    ":sortValue",
+                    AttributeValue::S(sort_value.to_owned()),
+                ),
+            SortKeyCondition::GreaterThan(sort_value) => query
+                .key_condition_expression(
+                    "#partition_key = :valueToMatch AND #sort_key > :sortValue",
+                )
+                .expression_attribute_names("#sort_key", SORT_KEY_NAME)
+                .expression_attribute_values(
+                    ":sortValue",
+                    AttributeValue::S(sort_value.to_owned()),
+                ),
+            SortKeyCondition::Between(low, high) => query
+                .key_condition_expression(
+                    "#partition_key = :valueToMatch AND #sort_key BETWEEN :low AND :high",
+                )
+                .expression_attribute_names("#sort_key", SORT_KEY_NAME)
+                .expression_attribute_values(":low", AttributeValue::S(low.to_owned()))
+                .expression_attribute_values(":high", AttributeValue::S(high.to_owned())),
+        };
+        let mut items = Vec::new();
+        let mut exclusive_start_key = None;
+        while items.len() < limit {
+            let output = query
+                .clone()
+                .limit(i32::try_from(limit - items.len()).unwrap_or(i32::MAX))
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await?;
+            items.extend(output.items.unwrap_or_default().into_iter().map(Item::from));
+            exclusive_start_key = output.last_evaluated_key;
+            if exclusive_start_key.is_none() {
+                break;
+            }
+        }
+        Ok(items)
+    }
+}
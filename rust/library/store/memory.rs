@@ -0,0 +1,355 @@
+use super::{AttributeValue, Item, SortKeyCondition, Store};
+use crate::activitypub::collection::{Cursor, Page};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::sync::{Mutex, MutexGuard};
+
+type Partitions = HashMap<String, BTreeMap<String, Item>>;
+
+/// A store kept in memory, for tests and local development.
+#[derive(Default)]
+pub struct MemoryStore {
+    partitions: Mutex<Partitions>,
+}
+
+impl MemoryStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Partitions> {
+        // a panic while holding the lock can't leave a partition half written
+        self.partitions
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Items of the partition from `start` on in sort key order, or in reverse if not `forward`.
+    fn items<'a>(
+        partitions: &'a Partitions,
+        partition: &str,
+        start: Bound<&str>,
+        forward: bool,
+    ) -> Box<dyn Iterator<Item = (&'a String, &'a Item)> + 'a> {
+        let Some(items) = partitions.get(partition) else {
+            return Box::new(std::iter::empty());
+        };
+        if forward {
+            Box::new(items.range::<str, _>((start, Bound::Unbounded)))
+        } else {
+            Box::new(items.range::<str, _>((Bound::Unbounded, start)).rev())
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Store for MemoryStore {
+    async fn get_item(
+        &self,
+        partition: &str,
+        sort_value: &str,
+        attributes: &[&str],
+    ) -> crate::Result<Option<Item>> {
+        let Some(item) = self
+            .lock()
+            .get(partition)
+            .and_then(|items| items.get(sort_value))
+            .cloned()
+        else {
+            return Ok(None);
+        };
+        if attributes.is_empty() {
+            return Ok(Some(item));
+        }
+
+        let projection = item
+            .iter()
+            .filter(|(name, _)| attributes.contains(&name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<HashMap<_, _>>();
+        Ok(Some(projection.into()))
+    }
+
+    async fn put_item(&self, partition: &str, sort_value: &str, item: Item) -> crate::Result<()> {
+        self.lock().entry(partition.to_owned()).or_default().insert(
+            sort_value.to_owned(),
+            super::with_keys(item, partition, sort_value),
+        );
+        Ok(())
+    }
+
+    async fn put_new_item(
+        &self,
+        partition: &str,
+        sort_value: &str,
+        item: Item,
+    ) -> crate::Result<bool> {
+        let mut inserted = false;
+        self.lock()
+            .entry(partition.to_owned())
+            .or_default()
+            .entry(sort_value.to_owned())
+            .or_insert_with(|| {
+                inserted = true;
+                super::with_keys(item, partition, sort_value)
+            });
+        Ok(inserted)
+    }
+
+    async fn delete_item(&self, partition: &str, sort_value: &str) -> crate::Result<()> {
+        self.take_item(partition, sort_value).await?;
+        Ok(())
+    }
+
+    async fn replace_item_if(
+        &self,
+        partition: &str,
+        sort_value: &str,
+        item: Item,
+        attribute: &str,
+        expected: Option<&AttributeValue>,
+    ) -> crate::Result<bool> {
+        let mut partitions = self.lock();
+        let Some(existing) = partitions
+            .get_mut(partition)
+            .and_then(|items| items.get_mut(sort_value))
+        else {
+            return Ok(false);
+        };
+        if existing.get(attribute) != expected {
+            return Ok(false);
+        }
+
+        *existing = super::with_keys(item, partition, sort_value);
+        drop(partitions);
+        Ok(true)
+    }
+
+    async fn take_item(&self, partition: &str, sort_value: &str) -> crate::Result<bool> {
+        Ok(self
+            .lock()
+            .get_mut(partition)
+            .is_some_and(|items| items.remove(sort_value).is_some()))
+    }
+
+    async fn count(&self, partition: &str, only_if: Option<&str>) -> crate::Result<i32> {
+        let count = Self::items(&self.lock(), partition, Bound::Unbounded, true)
+            .filter(|(_, item)| super::is_true(item, only_if))
+            .count();
+        Ok(i32::try_from(count).unwrap_or(i32::MAX))
+    }
+
+    async fn get_page(
+        &self,
+        partition: &str,
+        only_if: Option<&str>,
+        cursor: Cursor<'_>,
+        page_size: usize,
+    ) -> crate::Result<Page<Item>> {
+        let (start, forward) = match cursor {
+            Cursor::First => (Bound::Unbounded, true),
+            Cursor::After(sort_value) => (Bound::Excluded(sort_value), true),
+            Cursor::Before(sort_value) => (Bound::Excluded(sort_value), false),
+        };
+        let items = Self::items(&self.lock(), partition, start, forward)
+            .map(|(_, item)| item)
+            .filter(|item| super::is_true(item, only_if))
+            .take(page_size + 1)
+            .cloned()
+            .collect();
+        Ok(super::to_page(items, cursor, page_size))
+    }
+
+    async fn get_range(
+        &self,
+        partition: &str,
+        condition: SortKeyCondition<'_>,
+        scan_index_forward: bool,
+        limit: usize,
+    ) -> crate::Result<Vec<Item>> {
+        let partitions = self.lock();
+        Ok(
+            Self::items(&partitions, partition, Bound::Unbounded, scan_index_forward)
+                .filter(|(sort_value, _)| condition.matches(sort_value))
+                .take(limit)
+                .map(|(_, item)| item.clone())
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str) -> Item {
+        HashMap::from([("name".to_owned(), AttributeValue::S(name.to_owned()))]).into()
+    }
+
+    #[tokio::test]
+    async fn test_get_item() {
+        // Arrange
+        let store = MemoryStore::new();
+        store.put_item("users/a", "user", item("a")).await.unwrap();
+
+        // Act
+        let actual = store.get_item("users/a", "user", &["name"]).await.unwrap();
+
+        // Assert
+        assert_eq!(actual, Some(item("a")));
+        assert_eq!(store.get_item("users/a", "other", &[]).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_put_new_item_take_item() {
+        // Arrange
+        let store = MemoryStore::new();
+        store.put_item("deliveries", "1", item("a")).await.unwrap();
+
+        // Act
+        let replaced = store
+            .put_new_item("deliveries", "1", item("b"))
+            .await
+            .unwrap();
+        let inserted = store
+            .put_new_item("deliveries", "2", item("c"))
+            .await
+            .unwrap();
+        let taken = store.take_item("deliveries", "1").await.unwrap();
+        let taken_again = store.take_item("deliveries", "1").await.unwrap();
+
+        // Assert
+        assert!(!replaced);
+        assert!(inserted);
+        assert!(taken);
+        assert!(!taken_again);
+        assert_eq!(
+            store.get_item("deliveries", "2", &["name"]).await.unwrap(),
+            Some(item("c"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replace_item_if() {
+        // Arrange
+        let store = MemoryStore::new();
+        store.put_item("fan_outs", "1", item("a")).await.unwrap();
+
+        // Act
+        let stale = store
+            .replace_item_if(
+                "fan_outs",
+                "1",
+                item("b"),
+                "name",
+                Some(&AttributeValue::S("b".to_owned())),
+            )
+            .await
+            .unwrap();
+        let replaced = store
+            .replace_item_if(
+                "fan_outs",
+                "1",
+                item("b"),
+                "name",
+                Some(&AttributeValue::S("a".to_owned())),
+            )
+            .await
+            .unwrap();
+        let missing = store
+            .replace_item_if("fan_outs", "2", item("c"), "cursor", None)
+            .await
+            .unwrap();
+
+        // Assert
+        assert!(!stale);
+        assert!(replaced);
+        assert!(!missing);
+        assert_eq!(
+            store.get_item("fan_outs", "1", &["name"]).await.unwrap(),
+            Some(item("b"))
+        );
+        assert_eq!(store.get_item("fan_outs", "2", &[]).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_get_page() {
+        // Arrange
+        let store = MemoryStore::new();
+        for sort_value in ["a", "b", "c", "d", "e"] {
+            store
+                .put_item("followers", sort_value, item(sort_value))
+                .await
+                .unwrap();
+        }
+
+        // Act
+        let first = store
+            .get_page("followers", None, Cursor::First, 2)
+            .await
+            .unwrap();
+        let before = store
+            .get_page("followers", None, Cursor::Before("e"), 2)
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(first.next.as_deref(), Some("b"));
+        assert_eq!(first.prev, None);
+        assert_eq!(
+            before
+                .items
+                .iter()
+                .filter_map(super::super::get_sort_value)
+                .collect::<Vec<_>>(),
+            ["c", "d"]
+        );
+        assert_eq!(before.next.as_deref(), Some("d"));
+        assert_eq!(before.prev.as_deref(), Some("c"));
+    }
+
+    #[tokio::test]
+    async fn test_count_only_if() {
+        // Arrange
+        let store = MemoryStore::new();
+        let mut accepted = item("a");
+        accepted.insert("accepted".to_owned(), AttributeValue::Bool(true));
+        store.put_item("following", "a", accepted).await.unwrap();
+        store.put_item("following", "b", item("b")).await.unwrap();
+
+        // Act
+        let actual = store.count("following", Some("accepted")).await.unwrap();
+
+        // Assert
+        assert_eq!(actual, 1);
+        assert_eq!(store.count("following", None).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_range() {
+        // Arrange
+        let store = MemoryStore::new();
+        for sort_value in ["1", "2", "3", "4"] {
+            store
+                .put_item("statuses", sort_value, item(sort_value))
+                .await
+                .unwrap();
+        }
+
+        // Act
+        let actual = store
+            .get_range("statuses", SortKeyCondition::Between("2", "4"), false, 2)
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(
+            actual
+                .iter()
+                .filter_map(super::super::get_sort_value)
+                .collect::<Vec<_>>(),
+            ["4", "3"]
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use crate::activitypub::collection::{Cursor, Page};
+use serde::{de::DeserializeOwned, Serialize};
+
+mod dynamodb;
+mod memory;
+mod sqlite;
+
+pub use dynamodb::DynamoDbStore;
+pub use memory::MemoryStore;
+pub use serde_dynamo::{AttributeValue, Item};
+pub use sqlite::SqliteStore;
+
+pub const PARTITION_KEY_NAME: &str = "partition_key";
+pub const SORT_KEY_NAME: &str = "sort_key";
+
+/// Uses the `SQLite` database at `SQLITE_DATABASE` when set, the `DynamoDB` table otherwise.
+///
+/// # Panics
+///
+/// Will panic if the database can't be opened or `DYNAMODB_TABLE` isn't set.
+pub async fn from_env() -> Box<dyn Store> {
+    match std::env::var("SQLITE_DATABASE") {
+        Ok(path) => Box::new(SqliteStore::open(&path).unwrap()),
+        Err(_) => Box::new(DynamoDbStore::new(
+            crate::dynamodb::get_client().await,
+            std::env::var("DYNAMODB_TABLE").unwrap(),
+        )),
+    }
+}
+
+/// Condition on the sort key of the items to query.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SortKeyCondition<'a> {
+    Any,
+    LessThan(&'a str),
+    GreaterThan(&'a str),
+    /// Inclusive on both ends.
+    Between(&'a str, &'a str),
+}
+
+impl SortKeyCondition<'_> {
+    #[must_use]
+    pub fn matches(&self, sort_value: &str) -> bool {
+        match *self {
+            Self::Any => true,
+            Self::LessThan(bound) => sort_value < bound,
+            Self::GreaterThan(bound) => sort_value > bound,
+            Self::Between(low, high) => low <= sort_value && sort_value <= high,
+        }
+    }
+}
+
+/// Items grouped in partitions and ordered by their sort key inside each partition.
+///
+/// Stored items carry their keys in the `partition_key` and `sort_key` attributes.
+#[rocket::async_trait]
+pub trait Store: Send + Sync {
+    /// Gets the item with only the listed attributes, all of them if the list is empty.
+    async fn get_item(
+        &self,
+        partition: &str,
+        sort_value: &str,
+        attributes: &[&str],
+    ) -> crate::Result<Option<Item>>;
+
+    /// Inserts the item, replacing the one with the same keys.
+    async fn put_item(&self, partition: &str, sort_value: &str, item: Item) -> crate::Result<()>;
+
+    /// Inserts the item unless there is one with the same keys, returning whether it did.
+    async fn put_new_item(
+        &self,
+        partition: &str,
+        sort_value: &str,
+        item: Item,
+    ) -> crate::Result<bool>;
+
+    /// Replaces the item only while its `attribute` is still `expected`, or missing when `None`,
+    /// returning whether it did.
+    async fn replace_item_if(
+        &self,
+        partition: &str,
+        sort_value: &str,
+        item: Item,
+        attribute: &str,
+        expected: Option<&AttributeValue>,
+    ) -> crate::Result<bool>;
+
+    async fn delete_item(&self, partition: &str, sort_value: &str) -> crate::Result<()>;
+
+    /// Deletes the item, returning whether it was there.
+    ///
+    /// When several callers take the same item at once, only one of them gets `true`.
+    async fn take_item(&self, partition: &str, sort_value: &str) -> crate::Result<bool>;
+
+    /// Counts the items in a partition.
+    ///
+    /// When `only_if` names a boolean attribute, items where it isn't `true` aren't counted.
+    async fn count(&self, partition: &str, only_if: Option<&str>) -> crate::Result<i32>;
+
+    /// Gets up to `page_size` items of a partition in sort key order, starting from `cursor`.
+    ///
+    /// When `only_if` names a boolean attribute, items where it isn't `true` are skipped.
+    async fn get_page(
+        &self,
+        partition: &str,
+        only_if: Option<&str>,
+        cursor: Cursor<'_>,
+        page_size: usize,
+    ) -> crate::Result<Page<Item>>;
+
+    /// Gets up to `limit` items of a partition whose sort key satisfies `condition`.
+    async fn get_range(
+        &self,
+        partition: &str,
+        condition: SortKeyCondition<'_>,
+        scan_index_forward: bool,
+        limit: usize,
+    ) -> crate::Result<Vec<Item>>;
+}
+
+/// Gets an item and deserializes it.
+///
+/// # Errors
+///
+/// Will return `Err` if the store fails or the item doesn't deserialize to `T`.
+pub async fn get<T: DeserializeOwned>(
+    store: &dyn Store,
+    partition: &str,
+    sort_value: &str,
+    attributes: &[&str],
+) -> crate::Result<Option<T>> {
+    match store.get_item(partition, sort_value, attributes).await? {
+        Some(item) => Ok(Some(serde_dynamo::from_item(item)?)),
+        None => Ok(None),
+    }
+}
+
+/// Serializes `value` and puts it as an item.
+///
+/// # Errors
+///
+/// Will return `Err` if `value` doesn't serialize to a map or the store fails.
+pub async fn put<T: Serialize + Sync>(
+    store: &dyn Store,
+    partition: &str,
+    sort_value: &str,
+    value: &T,
+) -> crate::Result<()> {
+    let item: Item = serde_dynamo::to_item(value)?;
+    store.put_item(partition, sort_value, item).await
+}
+
+/// Deserializes the items of a page.
+///
+/// # Errors
+///
+/// Will return `Err` if an item doesn't deserialize to `T`.
+pub fn from_items<T: DeserializeOwned>(items: Vec<Item>) -> crate::Result<Vec<T>> {
+    Ok(items
+        .into_iter()
+        .map(serde_dynamo::from_item)
+        .collect::<Result<_, _>>()?)
+}
+
+#[must_use]
+pub fn get_sort_value(item: &Item) -> Option<String> {
+    match item.get(SORT_KEY_NAME) {
+        Some(AttributeValue::S(sort_value)) => Some(sort_value.clone()),
+        _ => None,
+    }
+}
+
+/// Adds the keys to the attributes of the item, as the store keeps them.
+fn with_keys(mut item: Item, partition: &str, sort_value: &str) -> Item {
+    item.insert(
+        PARTITION_KEY_NAME.to_owned(),
+        AttributeValue::S(partition.to_owned()),
+    );
+    item.insert(
+        SORT_KEY_NAME.to_owned(),
+        AttributeValue::S(sort_value.to_owned()),
+    );
+    item
+}
+
+/// Whether the item passes the `only_if` filter of [`Store::count`] and [`Store::get_page`].
+fn is_true(item: &Item, only_if: Option<&str>) -> bool {
+    only_if.is_none_or(|attribute_name| {
+        matches!(item.get(attribute_name), Some(AttributeValue::Bool(true)))
+    })
+}
+
+/// Builds a page out of up to `page_size + 1` items read in the direction of `cursor`.
+fn to_page(mut items: Vec<Item>, cursor: Cursor<'_>, page_size: usize) -> Page<Item> {
+    let has_more = items.len() > page_size;
+    items.truncate(page_size);
+    if matches!(cursor, Cursor::Before(_)) {
+        items.reverse();
+    }
+
+    let (has_next, has_prev) = match cursor {
+        Cursor::First => (has_more, false),
+        Cursor::After(_) => (has_more, true),
+        Cursor::Before(_) => (true, has_more),
+    };
+    Page {
+        next: items.last().filter(|_| has_next).and_then(get_sort_value),
+        prev: items.first().filter(|_| has_prev).and_then(get_sort_value),
+        items,
+    }
+}
@@ -0,0 +1,534 @@
+use super::{AttributeValue, Item, SortKeyCondition, Store};
+use crate::activitypub::collection::{Cursor, Page};
+use crate::Error;
+use base64::{engine::general_purpose, Engine as _};
+use rusqlite::{params, params_from_iter, Connection, Params};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+/// A store in a single `SQLite` table, for running the service without AWS.
+///
+/// Items are saved as `DynamoDB` JSON, so that binary and number attributes keep their type.
+/// Queries block the calling thread, which is fine for the load of a small instance.
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Opens the database file, creating it and its table if needed.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the database can´t be opened or the table created.
+    pub fn open(path: &str) -> crate::Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the table can´t be created.
+    pub fn open_in_memory() -> crate::Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> crate::Result<Self> {
+        // the BINARY collation orders sort keys byte by byte, like DynamoDB does
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS items (
+                partition_key TEXT NOT NULL,
+                sort_key TEXT NOT NULL,
+                item TEXT NOT NULL,
+                PRIMARY KEY (partition_key, sort_key)
+            ) WITHOUT ROWID",
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Runs an `INSERT` of the item with the conflict resolution of `insert`,
+    /// returning the number of rows it inserted.
+    fn insert(
+        &self,
+        insert: &str,
+        partition: &str,
+        sort_value: &str,
+        item: Item,
+    ) -> crate::Result<usize> {
+        let item = super::with_keys(item, partition, sort_value);
+        let encoded = Value::Object(encode_map(&item)).to_string();
+        Ok(self.lock().execute(
+            &format!("{insert} INTO items (partition_key, sort_key, item) VALUES (?1, ?2, ?3)"),
+            params![partition, sort_value, encoded],
+        )?)
+    }
+
+    /// Gets up to `limit` items of the partition that pass `only_if`.
+    ///
+    /// `bounds` is appended to the `WHERE` clause and may use `?2` and `?3` for `values`.
+    fn select(
+        &self,
+        partition: &str,
+        bounds: &str,
+        values: &[&str],
+        forward: bool,
+        only_if: Option<&str>,
+        limit: usize,
+    ) -> crate::Result<Vec<Item>> {
+        let order = if forward { "ASC" } else { "DESC" };
+        let sql = format!(
+            "SELECT item FROM items WHERE partition_key = ?1 {bounds} ORDER BY sort_key {order}"
+        );
+        let params = params_from_iter(std::iter::once(&partition).chain(values));
+        query_items(&self.lock(), &sql, params, only_if, limit)
+    }
+}
+
+/// Reads the items of the rows until `limit` of them pass `only_if`.
+fn query_items(
+    connection: &Connection,
+    sql: &str,
+    params: impl Params,
+    only_if: Option<&str>,
+    limit: usize,
+) -> crate::Result<Vec<Item>> {
+    let mut statement = connection.prepare(sql)?;
+    let mut rows = statement.query(params)?;
+    let mut items = Vec::new();
+    while items.len() < limit {
+        let Some(row) = rows.next()? else {
+            break;
+        };
+        let item = decode_item(&row.get::<_, String>(0)?)?;
+        if super::is_true(&item, only_if) {
+            items.push(item);
+        }
+    }
+    Ok(items)
+}
+
+#[rocket::async_trait]
+impl Store for SqliteStore {
+    async fn get_item(
+        &self,
+        partition: &str,
+        sort_value: &str,
+        attributes: &[&str],
+    ) -> crate::Result<Option<Item>> {
+        let Some(mut item) = self
+            .select(partition, "AND sort_key = ?2", &[sort_value], true, None, 1)?
+            .pop()
+        else {
+            return Ok(None);
+        };
+        if !attributes.is_empty() {
+            item.retain(|name, _| attributes.contains(&name.as_str()));
+        }
+        Ok(Some(item))
+    }
+
+    async fn put_item(&self, partition: &str, sort_value: &str, item: Item) -> crate::Result<()> {
+        self.insert("INSERT OR REPLACE", partition, sort_value, item)?;
+        Ok(())
+    }
+
+    async fn put_new_item(
+        &self,
+        partition: &str,
+        sort_value: &str,
+        item: Item,
+    ) -> crate::Result<bool> {
+        Ok(self.insert("INSERT OR IGNORE", partition, sort_value, item)? == 1)
+    }
+
+    /// Compares the attribute in the `DynamoDB` JSON of the item, in the same statement that
+    /// replaces it, as other processes may share the database file.
+    async fn replace_item_if(
+        &self,
+        partition: &str,
+        sort_value: &str,
+        item: Item,
+        attribute: &str,
+        expected: Option<&AttributeValue>,
+    ) -> crate::Result<bool> {
+        let item = super::with_keys(item, partition, sort_value);
+        let encoded = Value::Object(encode_map(&item)).to_string();
+        let path = format!("$.\"{attribute}\"");
+        let expected = expected.map(|expected| encode(expected).to_string());
+        let replaced = self.lock().execute(
+            "UPDATE items SET item = ?3
+            WHERE partition_key = ?1 AND sort_key = ?2 AND json_extract(item, ?4) IS ?5",
+            params![partition, sort_value, encoded, path, expected],
+        )?;
+        Ok(replaced == 1)
+    }
+
+    async fn delete_item(&self, partition: &str, sort_value: &str) -> crate::Result<()> {
+        self.take_item(partition, sort_value).await?;
+        Ok(())
+    }
+
+    async fn take_item(&self, partition: &str, sort_value: &str) -> crate::Result<bool> {
+        let deleted = self.lock().execute(
+            "DELETE FROM items WHERE partition_key = ?1 AND sort_key = ?2",
+            params![partition, sort_value],
+        )?;
+        Ok(deleted == 1)
+    }
+
+    async fn count(&self, partition: &str, only_if: Option<&str>) -> crate::Result<i32> {
+        if only_if.is_some() {
+            let items = self.select(partition, "", &[], true, only_if, usize::MAX)?;
+            return Ok(i32::try_from(items.len()).unwrap_or(i32::MAX));
+        }
+
+        Ok(self.lock().query_row(
+            "SELECT COUNT(*) FROM items WHERE partition_key = ?1",
+            params![partition],
+            |row| row.get(0),
+        )?)
+    }
+
+    async fn get_page(
+        &self,
+        partition: &str,
+        only_if: Option<&str>,
+        cursor: Cursor<'_>,
+        page_size: usize,
+    ) -> crate::Result<Page<Item>> {
+        let items = match cursor {
+            Cursor::First => self.select(partition, "", &[], true, only_if, page_size + 1)?,
+            Cursor::After(sort_value) => self.select(
+                partition,
+                "AND sort_key > ?2",
+                &[sort_value],
+                true,
+                only_if,
+                page_size + 1,
+            )?,
+            Cursor::Before(sort_value) => self.select(
+                partition,
+                "AND sort_key < ?2",
+                &[sort_value],
+                false,
+                only_if,
+                page_size + 1,
+            )?,
+        };
+        Ok(super::to_page(items, cursor, page_size))
+    }
+
+    async fn get_range(
+        &self,
+        partition: &str,
+        condition: SortKeyCondition<'_>,
+        scan_index_forward: bool,
+        limit: usize,
+    ) -> crate::Result<Vec<Item>> {
+        let (bounds, values) = match condition {
+            SortKeyCondition::Any => ("", vec![]),
+            SortKeyCondition::LessThan(sort_value) => ("AND sort_key < ?2", vec![sort_value]),
+            SortKeyCondition::GreaterThan(sort_value) => ("AND sort_key > ?2", vec![sort_value]),
+            SortKeyCondition::Between(low, high) => {
+                ("AND sort_key BETWEEN ?2 AND ?3", vec![low, high])
+            }
+        };
+        self.select(partition, bounds, &values, scan_index_forward, None, limit)
+    }
+}
+
+fn encode(value: &AttributeValue) -> Value {
+    match value {
+        AttributeValue::N(number) => json!({ "N": number }),
+        AttributeValue::S(string) => json!({ "S": string }),
+        AttributeValue::Bool(boolean) => json!({ "BOOL": boolean }),
+        AttributeValue::B(bytes) => json!({ "B": general_purpose::STANDARD.encode(bytes) }),
+        AttributeValue::Null(is_null) => json!({ "NULL": is_null }),
+        AttributeValue::M(map) => json!({ "M": encode_map(map) }),
+        AttributeValue::L(list) => json!({ "L": list.iter().map(encode).collect::<Vec<_>>() }),
+        AttributeValue::Ss(strings) => json!({ "SS": strings }),
+        AttributeValue::Ns(numbers) => json!({ "NS": numbers }),
+        AttributeValue::Bs(list) => json!({
+            "BS": list
+                .iter()
+                .map(|bytes| general_purpose::STANDARD.encode(bytes))
+                .collect::<Vec<_>>()
+        }),
+    }
+}
+
+fn encode_map(map: &HashMap<String, AttributeValue>) -> Map<String, Value> {
+    map.iter()
+        .map(|(name, value)| (name.clone(), encode(value)))
+        .collect()
+}
+
+fn decode(value: Value) -> crate::Result<AttributeValue> {
+    let Value::Object(typed) = value else {
+        return Err(Error::Parse(format!("untyped attribute value {value}")));
+    };
+    let mut typed = typed.into_iter();
+    let (Some((tag, value)), None) = (typed.next(), typed.next()) else {
+        return Err(Error::Parse(
+            "attribute value without exactly one type".to_owned(),
+        ));
+    };
+    Ok(match (tag.as_str(), value) {
+        ("N", Value::String(number)) => AttributeValue::N(number),
+        ("S", Value::String(string)) => AttributeValue::S(string),
+        ("BOOL", Value::Bool(boolean)) => AttributeValue::Bool(boolean),
+        ("B", Value::String(bytes)) => AttributeValue::B(decode_base64(&bytes)?),
+        ("NULL", Value::Bool(is_null)) => AttributeValue::Null(is_null),
+        ("M", Value::Object(map)) => AttributeValue::M(decode_map(map)?),
+        ("L", Value::Array(list)) => {
+            AttributeValue::L(list.into_iter().map(decode).collect::<crate::Result<_>>()?)
+        }
+        ("SS", strings) => AttributeValue::Ss(serde_json::from_value(strings)?),
+        ("NS", numbers) => AttributeValue::Ns(serde_json::from_value(numbers)?),
+        ("BS", list) => AttributeValue::Bs(
+            serde_json::from_value::<Vec<String>>(list)?
+                .iter()
+                .map(|bytes| decode_base64(bytes))
+                .collect::<crate::Result<_>>()?,
+        ),
+        (tag, value) => {
+            return Err(Error::Parse(format!(
+                "invalid attribute value of type {tag}: {value}"
+            )))
+        }
+    })
+}
+
+fn decode_map(map: Map<String, Value>) -> crate::Result<HashMap<String, AttributeValue>> {
+    map.into_iter()
+        .map(|(name, value)| Ok((name, decode(value)?)))
+        .collect()
+}
+
+fn decode_item(text: &str) -> crate::Result<Item> {
+    Ok(decode_map(serde_json::from_str(text)?)?.into())
+}
+
+fn decode_base64(text: &str) -> crate::Result<Vec<u8>> {
+    general_purpose::STANDARD
+        .decode(text)
+        .map_err(|err| Error::Parse(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{PARTITION_KEY_NAME, SORT_KEY_NAME};
+
+    fn item(name: &str) -> Item {
+        HashMap::from([("name".to_owned(), AttributeValue::S(name.to_owned()))]).into()
+    }
+
+    #[tokio::test]
+    async fn test_put_item_keeps_types() {
+        // Arrange
+        let store = SqliteStore::open_in_memory().unwrap();
+        let mut user = item("a");
+        user.insert("public_key".to_owned(), AttributeValue::B(vec![0, 1, 255]));
+        user.insert("hide_followers".to_owned(), AttributeValue::Bool(true));
+        user.insert(
+            "published_unix_time_seconds".to_owned(),
+            AttributeValue::N("1674086400".to_owned()),
+        );
+        user.insert(
+            "tag".to_owned(),
+            AttributeValue::L(vec![AttributeValue::M(HashMap::from([(
+                "type".to_owned(),
+                AttributeValue::S("Hashtag".to_owned()),
+            )]))]),
+        );
+
+        // Act
+        store
+            .put_item("users/a", "user", user.clone())
+            .await
+            .unwrap();
+        let actual = store.get_item("users/a", "user", &[]).await.unwrap();
+
+        // Assert
+        user.insert(
+            PARTITION_KEY_NAME.to_owned(),
+            AttributeValue::S("users/a".to_owned()),
+        );
+        user.insert(
+            SORT_KEY_NAME.to_owned(),
+            AttributeValue::S("user".to_owned()),
+        );
+        assert_eq!(actual, Some(user));
+        assert_eq!(
+            store.get_item("users/a", "user", &["name"]).await.unwrap(),
+            Some(item("a"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_put_new_item_take_item() {
+        // Arrange
+        let store = SqliteStore::open_in_memory().unwrap();
+        store.put_item("deliveries", "1", item("a")).await.unwrap();
+
+        // Act
+        let replaced = store
+            .put_new_item("deliveries", "1", item("b"))
+            .await
+            .unwrap();
+        let inserted = store
+            .put_new_item("deliveries", "2", item("c"))
+            .await
+            .unwrap();
+        let taken = store.take_item("deliveries", "1").await.unwrap();
+        let taken_again = store.take_item("deliveries", "1").await.unwrap();
+
+        // Assert
+        assert!(!replaced);
+        assert!(inserted);
+        assert!(taken);
+        assert!(!taken_again);
+        assert_eq!(
+            store.get_item("deliveries", "2", &["name"]).await.unwrap(),
+            Some(item("c"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replace_item_if() {
+        // Arrange
+        let store = SqliteStore::open_in_memory().unwrap();
+        store.put_item("fan_outs", "1", item("a")).await.unwrap();
+
+        // Act
+        let stale = store
+            .replace_item_if(
+                "fan_outs",
+                "1",
+                item("b"),
+                "name",
+                Some(&AttributeValue::S("b".to_owned())),
+            )
+            .await
+            .unwrap();
+        let replaced = store
+            .replace_item_if(
+                "fan_outs",
+                "1",
+                item("b"),
+                "name",
+                Some(&AttributeValue::S("a".to_owned())),
+            )
+            .await
+            .unwrap();
+        let missing = store
+            .replace_item_if("fan_outs", "2", item("c"), "cursor", None)
+            .await
+            .unwrap();
+
+        // Assert
+        assert!(!stale);
+        assert!(replaced);
+        assert!(!missing);
+        assert_eq!(
+            store.get_item("fan_outs", "1", &["name"]).await.unwrap(),
+            Some(item("b"))
+        );
+        assert_eq!(store.get_item("fan_outs", "2", &[]).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_replace_item_if_number() {
+        // Arrange
+        let store = SqliteStore::open_in_memory().unwrap();
+        let mut leased = item("a");
+        leased.insert("leased".to_owned(), AttributeValue::N("5".to_owned()));
+        store.put_item("deliveries", "1", leased).await.unwrap();
+
+        // Act
+        let actual = store
+            .replace_item_if(
+                "deliveries",
+                "1",
+                item("b"),
+                "leased",
+                Some(&AttributeValue::N("5".to_owned())),
+            )
+            .await
+            .unwrap();
+
+        // Assert
+        assert!(actual);
+        assert_eq!(
+            store.get_item("deliveries", "1", &["name"]).await.unwrap(),
+            Some(item("b"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_page() {
+        // Arrange
+        let store = SqliteStore::open_in_memory().unwrap();
+        for sort_value in ["a", "b", "c", "d", "e"] {
+            store
+                .put_item("followers", sort_value, item(sort_value))
+                .await
+                .unwrap();
+        }
+        store.delete_item("followers", "a").await.unwrap();
+
+        // Act
+        let actual = store
+            .get_page("followers", None, Cursor::Before("e"), 2)
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(
+            actual
+                .items
+                .iter()
+                .filter_map(super::super::get_sort_value)
+                .collect::<Vec<_>>(),
+            ["c", "d"]
+        );
+        assert_eq!(actual.next.as_deref(), Some("d"));
+        assert_eq!(actual.prev.as_deref(), Some("c"));
+        assert_eq!(store.count("followers", None).await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_get_range() {
+        // Arrange
+        let store = SqliteStore::open_in_memory().unwrap();
+        for sort_value in ["1", "2", "3", "4"] {
+            store
+                .put_item("statuses", sort_value, item(sort_value))
+                .await
+                .unwrap();
+        }
+
+        // Act
+        let actual = store
+            .get_range("statuses", SortKeyCondition::Between("2", "4"), false, 2)
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(
+            actual
+                .iter()
+                .filter_map(super::super::get_sort_value)
+                .collect::<Vec<_>>(),
+            ["4", "3"]
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use aws_lambda_events_extended::dynamodb::AttributeValue as StreamAttributeValue;
+use aws_sdk_dynamodb::primitives::Blob;
+use aws_sdk_dynamodb::types::AttributeValue;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+/// The new or old image of an item in a `DynamoDB` stream record.
+pub type StreamImage = HashMap<String, StreamAttributeValue>;
+
+/// Converts a stream image to an item, as it would be read from the table.
+///
+/// # Errors
+///
+/// Will return `Err` if any of the attribute values is empty.
+pub fn from_stream_image(image: StreamImage) -> crate::Result<HashMap<String, AttributeValue>> {
+    image
+        .into_iter()
+        .map(|(name, value)| Ok((name, from_stream_value(value)?)))
+        .collect()
+}
+
+/// # Errors
+///
+/// Will return `Err` if the attribute value, or any nested in it, is empty.
+pub fn from_stream_value(value: StreamAttributeValue) -> crate::Result<AttributeValue> {
+    if let Some(b) = value.b {
+        return Ok(AttributeValue::B(Blob::new(b.to_vec())));
+    }
+    if let Some(bool) = value.bool {
+        return Ok(AttributeValue::Bool(bool));
+    }
+    if let Some(bs) = value.bs {
+        return Ok(AttributeValue::Bs(
+            bs.into_iter().map(|b| Blob::new(b.to_vec())).collect(),
+        ));
+    }
+    if let Some(l) = value.l {
+        return Ok(AttributeValue::L(
+            l.into_iter()
+                .map(from_stream_value)
+                .collect::<crate::Result<_>>()?,
+        ));
+    }
+    if let Some(m) = value.m {
+        return Ok(AttributeValue::M(from_stream_image(m)?));
+    }
+    if let Some(n) = value.n {
+        return Ok(AttributeValue::N(n));
+    }
+    if let Some(ns) = value.ns {
+        return Ok(AttributeValue::Ns(ns));
+    }
+    if let Some(null) = value.null {
+        return Ok(AttributeValue::Null(null));
+    }
+    if let Some(s) = value.s {
+        return Ok(AttributeValue::S(s));
+    }
+    if let Some(ss) = value.ss {
+        return Ok(AttributeValue::Ss(ss));
+    }
+
+    Err(crate::Error::Parse("empty attribute value".to_owned()))
+}
+
+/// Converts an item to the image a stream record would carry of it.
+///
+/// # Errors
+///
+/// Will return `Err` if any of the attribute values is of a type unknown to this SDK version.
+pub fn to_stream_image<S: BuildHasher>(
+    item: HashMap<String, AttributeValue, S>,
+) -> crate::Result<StreamImage> {
+    item.into_iter()
+        .map(|(name, value)| Ok((name, to_stream_value(value)?)))
+        .collect()
+}
+
+/// # Errors
+///
+/// Will return `Err` if the attribute value, or any nested in it, is of an unknown type.
+pub fn to_stream_value(value: AttributeValue) -> crate::Result<StreamAttributeValue> {
+    let mut stream_value = StreamAttributeValue::default();
+    match value {
+        AttributeValue::B(b) => stream_value.b = Some(b.into_inner().into()),
+        AttributeValue::Bool(bool) => stream_value.bool = Some(bool),
+        AttributeValue::Bs(bs) => {
+            stream_value.bs = Some(bs.into_iter().map(|b| b.into_inner().into()).collect());
+        }
+        AttributeValue::L(l) => {
+            stream_value.l = Some(
+                l.into_iter()
+                    .map(to_stream_value)
+                    .collect::<crate::Result<_>>()?,
+            );
+        }
+        AttributeValue::M(m) => stream_value.m = Some(to_stream_image(m)?),
+        AttributeValue::N(n) => stream_value.n = Some(n),
+        AttributeValue::Ns(ns) => stream_value.ns = Some(ns),
+        AttributeValue::Null(null) => stream_value.null = Some(null),
+        AttributeValue::S(s) => stream_value.s = Some(s),
+        AttributeValue::Ss(ss) => stream_value.ss = Some(ss),
+        _ => return Err(crate::Error::Parse("unknown attribute value".to_owned())),
+    }
+    Ok(stream_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream_s(s: &str) -> StreamAttributeValue {
+        StreamAttributeValue {
+            s: Some(s.to_owned()),
+            ..StreamAttributeValue::default()
+        }
+    }
+
+    #[test]
+    fn test_from_stream_image() {
+        // Arrange
+        let mut context = StreamImage::new();
+        context.insert("PropertyValue".to_owned(), stream_s("schema:PropertyValue"));
+        let mut image = StreamImage::new();
+        image.insert(
+            "partition_key".to_owned(),
+            stream_s("users/sample_user/statuses"),
+        );
+        image.insert(
+            "@context".to_owned(),
+            StreamAttributeValue {
+                l: Some(vec![
+                    stream_s("https://www.w3.org/ns/activitystreams"),
+                    StreamAttributeValue {
+                        m: Some(context),
+                        ..StreamAttributeValue::default()
+                    },
+                ]),
+                ..StreamAttributeValue::default()
+            },
+        );
+        let mut expected_context = HashMap::new();
+        expected_context.insert(
+            "PropertyValue".to_owned(),
+            AttributeValue::S("schema:PropertyValue".to_owned()),
+        );
+        let mut expected = HashMap::new();
+        expected.insert(
+            "partition_key".to_owned(),
+            AttributeValue::S("users/sample_user/statuses".to_owned()),
+        );
+        expected.insert(
+            "@context".to_owned(),
+            AttributeValue::L(vec![
+                AttributeValue::S("https://www.w3.org/ns/activitystreams".to_owned()),
+                AttributeValue::M(expected_context),
+            ]),
+        );
+
+        // Act
+        let actual = from_stream_image(image).unwrap();
+
+        // Assert
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Arrange
+        let values = [
+            AttributeValue::B(Blob::new(vec![0, 1, 255])),
+            AttributeValue::Bool(false),
+            AttributeValue::Bs(vec![Blob::new(vec![1]), Blob::new(Vec::new())]),
+            AttributeValue::L(vec![
+                AttributeValue::N("1".to_owned()),
+                AttributeValue::L(Vec::new()),
+            ]),
+            AttributeValue::M(HashMap::from([(
+                "a".to_owned(),
+                AttributeValue::Null(true),
+            )])),
+            AttributeValue::N("-3.14".to_owned()),
+            AttributeValue::Ns(vec!["1".to_owned(), "2".to_owned()]),
+            AttributeValue::Null(true),
+            AttributeValue::S(String::new()),
+            AttributeValue::Ss(vec!["a".to_owned()]),
+        ];
+
+        for expected in values {
+            // Act
+            let stream_value = to_stream_value(expected.clone()).unwrap();
+            let json = serde_json::to_string(&stream_value).unwrap();
+            let actual = from_stream_value(serde_json::from_str(&json).unwrap()).unwrap();
+
+            // Assert
+            assert_eq!(actual, expected, "{json}");
+        }
+    }
+
+    #[test]
+    fn test_from_stream_image_numbers_and_nulls() {
+        // Arrange
+        let json = r#"{"attempts":{"N":"3"},"in_reply_to":{"NULL":true}}"#;
+        let image: StreamImage = serde_json::from_str(json).unwrap();
+
+        // Act
+        let actual = from_stream_image(image).unwrap();
+
+        // Assert
+        assert_eq!(
+            actual,
+            HashMap::from([
+                ("attempts".to_owned(), AttributeValue::N("3".to_owned())),
+                ("in_reply_to".to_owned(), AttributeValue::Null(true)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_from_stream_value_empty() {
+        // Act
+        let actual = from_stream_value(StreamAttributeValue::default());
+
+        // Assert
+        assert!(matches!(actual, Err(crate::Error::Parse(_))));
+    }
+}
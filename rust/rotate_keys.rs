@@ -0,0 +1,24 @@
+use library::settings::Settings;
+use library::store::DynamoDbStore;
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        panic!("Usage: rotate_keys username domain dynamodb_table_name")
+    }
+    let domain = args[2].clone();
+    let settings = Settings {
+        base_url: format!("https://{domain}"),
+        domain_name: domain,
+        node_id: library::faas_snowflake_id::get_node_id(),
+        store: Box::new(DynamoDbStore::new(
+            library::dynamodb::get_client().await,
+            args[3].clone(),
+        )),
+    };
+    let settings = <&rocket::State<Settings>>::from(&settings);
+    library::model::user::rotate_keys(&args[1], settings)
+        .await
+        .unwrap();
+}
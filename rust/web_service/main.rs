@@ -13,10 +13,9 @@ async fn main() -> Result<(), LambdaError> {
         .mount("/", routes::routes())
         .manage(Settings {
             base_url: format!("{}://{domain_name}", var("PROTOCOL").unwrap()),
-            db_client: library::dynamodb::get_client().await,
             domain_name,
             node_id: library::faas_snowflake_id::get_node_id(),
-            table_name: var("DYNAMODB_TABLE").unwrap(),
+            store: library::store::from_env().await,
         });
 
     if is_running_on_lambda() {
@@ -14,9 +14,9 @@ pub fn routes() -> Vec<rocket::Route> {
 async fn handler(
     path: &str,
     settings: &rocket::State<library::settings::Settings>,
-) -> Option<users::UserResponse> {
+) -> Result<users::UserResponse, library::Error> {
     if path.len() < 2 || !path.starts_with('@') {
-        return None;
+        return Err(library::Error::NotFound(path.to_owned()));
     }
     let username = &path[1..];
     users::handler(username, settings).await
@@ -1,20 +1,48 @@
+use library::activitypub::collection::{self, Cursor};
+use library::model::{follower, user};
+use library::Error;
 use rocket::http::ContentType;
 use rocket::serde::json::Json;
+use tracing::{event, Level};
+
+const PAGE_SIZE: usize = 20;
 
 #[derive(rocket::Responder)]
 pub struct Followers(Json<serde_json::Value>, ContentType);
 
 #[rocket::get("/users/<username>/followers")]
-pub fn handler(username: &str, settings: &rocket::State<library::settings::Settings>) -> Followers {
-    let user_uri = format!("{}/users/{username}", settings.base_url);
-    let body = Json(serde_json::json!({
-        "@context": "https://www.w3.org/ns/activitystreams",
-        "id": format!("{user_uri}/followers"),
-        "type": "OrderedCollection",
-        "totalItems": 1, // TODO: real number from db
-        "first": format!("{user_uri}/followers?page=1"),
-    }));
-    let content_type: ContentType =
-        ContentType::new("application", "activity+json").with_params([("charset", "utf-8")]);
-    Followers(body, content_type)
+pub async fn handler(
+    username: &str,
+    settings: &rocket::State<library::settings::Settings>,
+) -> Result<Followers, Error> {
+    let user = user::get_existing(username, settings).await?;
+    let id = format!("{}/users/{username}/followers", settings.base_url);
+    let total_items = follower::count(username, settings).await?;
+    let body = collection::ordered_collection(&id, total_items, user.hide_followers);
+    Ok(Followers(Json(body), content_type()))
+}
+
+#[rocket::get("/users/<username>/followers?<page>&<after>&<before>")]
+pub async fn page(
+    username: &str,
+    page: &str,
+    after: Option<&str>,
+    before: Option<&str>,
+    settings: &rocket::State<library::settings::Settings>,
+) -> Result<Followers, Error> {
+    event!(Level::DEBUG, username = username, page = page);
+    let user = user::get_existing(username, settings).await?;
+    if user.hide_followers {
+        return Err(Error::Forbidden(format!("followers of {username}")));
+    }
+
+    let id = format!("{}/users/{username}/followers", settings.base_url);
+    let cursor = Cursor::new(after, before);
+    let followers = follower::get_page(username, cursor, PAGE_SIZE, settings).await?;
+    let body = collection::ordered_collection_page(&id, cursor, followers);
+    Ok(Followers(Json(body), content_type()))
+}
+
+fn content_type() -> ContentType {
+    ContentType::new("application", "activity+json").with_params([("charset", "utf-8")])
 }
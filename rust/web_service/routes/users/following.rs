@@ -1,16 +1,44 @@
+use library::activitypub::collection::{self, Cursor};
+use library::model::{following, user};
+use library::Error;
+use rocket::http::ContentType;
 use rocket::serde::json::Json;
+use tracing::{event, Level};
+
+const PAGE_SIZE: usize = 20;
+
+#[derive(rocket::Responder)]
+pub struct Following(Json<serde_json::Value>, ContentType);
 
 #[rocket::get("/users/<username>/following")]
-pub fn handler(
+pub async fn handler(
+    username: &str,
+    settings: &rocket::State<library::settings::Settings>,
+) -> Result<Following, Error> {
+    user::get_existing(username, settings).await?;
+    let id = format!("{}/users/{username}/following", settings.base_url);
+    let total_items = following::count(username, settings).await?;
+    let body = collection::ordered_collection(&id, total_items, false);
+    Ok(Following(Json(body), content_type()))
+}
+
+#[rocket::get("/users/<username>/following?<page>&<after>&<before>")]
+pub async fn page(
     username: &str,
+    page: &str,
+    after: Option<&str>,
+    before: Option<&str>,
     settings: &rocket::State<library::settings::Settings>,
-) -> Json<serde_json::Value> {
-    let user_uri = format!("{}/users/{username}", settings.base_url);
-    Json(serde_json::json!({
-        "@context": "https://www.w3.org/ns/activitystreams",
-        "id": format!("{user_uri}/following"),
-        "type": "OrderedCollection",
-        "totalItems": 1, // TODO: real number from db
-        "first": format!("{user_uri}/following?page=1"),
-    }))
+) -> Result<Following, Error> {
+    event!(Level::DEBUG, username = username, page = page);
+    user::get_existing(username, settings).await?;
+    let id = format!("{}/users/{username}/following", settings.base_url);
+    let cursor = Cursor::new(after, before);
+    let followings = following::get_page(username, cursor, PAGE_SIZE, settings).await?;
+    let body = collection::ordered_collection_page(&id, cursor, followings);
+    Ok(Following(Json(body), content_type()))
+}
+
+fn content_type() -> ContentType {
+    ContentType::new("application", "activity+json").with_params([("charset", "utf-8")])
 }
@@ -0,0 +1,20 @@
+use library::activitypub::activity::Activity;
+use library::settings::Settings;
+
+pub async fn handle(
+    username: &str,
+    activity: &Activity,
+    value: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> library::Result<()> {
+    super::get_object_id(activity)?;
+    let partition = format!("users/{username}/blocked_by");
+    library::store::put(
+        settings.store.as_ref(),
+        partition.as_str(),
+        &activity.actor,
+        value,
+    )
+    .await?;
+    Ok(())
+}
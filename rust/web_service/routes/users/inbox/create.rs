@@ -0,0 +1,19 @@
+use library::activitypub::activity::Activity;
+use library::settings::Settings;
+
+/// Stores the object, which must be attributed to the actor, or the activity when the object is
+/// only referenced by its id.
+pub async fn handle(
+    username: &str,
+    activity: &Activity,
+    value: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> library::Result<()> {
+    let object_id = super::get_object_id(activity)?;
+    let object = if activity.object.is_object() {
+        &activity.object
+    } else {
+        value
+    };
+    library::model::inbox::put_object(username, &activity.actor, object_id, object, settings).await
+}
@@ -0,0 +1,12 @@
+use library::activitypub::activity::Activity;
+use library::settings::Settings;
+
+pub async fn handle(
+    username: &str,
+    activity: &Activity,
+    _value: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> library::Result<()> {
+    let object_id = super::get_object_id(activity)?;
+    library::model::inbox::delete_object(username, &activity.actor, object_id, settings).await
+}
@@ -0,0 +1,34 @@
+use library::activitypub::activity::Activity;
+use library::model::{follow_request, follower};
+use library::settings::Settings;
+use tracing::{event, Level};
+
+pub async fn handle(
+    username: &str,
+    activity: &Activity,
+    value: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> library::Result<()> {
+    let user_uri = format!("{}/users/{username}", settings.base_url);
+    if super::get_object_id(activity)? != user_uri {
+        return Err(library::Error::Invalid(format!(
+            "Follow object is not {user_uri}"
+        )));
+    }
+
+    let user = library::model::user::get_existing(username, settings).await?;
+    if user.manually_approves_followers {
+        follow_request::create(username, &activity.actor, value, settings).await?;
+        return Ok(());
+    }
+
+    follower::create(username, &activity.actor, value, settings).await?;
+    if let Err(err) = follower::accept(username, value, settings).await {
+        event!(
+            Level::WARN,
+            "failed to accept follow from {}: {err}",
+            activity.actor
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,13 @@
+use library::activitypub::activity::Activity;
+use library::settings::Settings;
+
+pub async fn handle(
+    username: &str,
+    activity: &Activity,
+    value: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> library::Result<()> {
+    let activity_id = super::get_activity_id(activity)?;
+    super::get_object_id(activity)?;
+    library::model::inbox::put_activity(username, "likes", activity_id, value, settings).await
+}
@@ -0,0 +1,85 @@
+use library::activitypub::activity::{Activity, ActivityType};
+use library::activitypub::signature;
+use library::settings::Settings;
+use library::Error;
+use tracing::{event, Level};
+
+mod accept;
+mod announce;
+mod block;
+mod create;
+mod delete;
+mod follow;
+mod like;
+mod r#move;
+mod reject;
+mod undo;
+mod update;
+
+#[rocket::post("/users/<username>/inbox", data = "<data>")]
+pub async fn handler(
+    username: &str,
+    headers: library::activitypub::headers::Headers<'_>,
+    data: String,
+    settings: &rocket::State<Settings>,
+) -> Result<String, Error> {
+    for header in headers.0.iter() {
+        event!(Level::DEBUG, "{}: {}", header.name(), header.value());
+    }
+
+    event!(Level::DEBUG, "body = {data}");
+    let value: serde_json::Value = serde_json::from_str(&data)?;
+    let activity = Activity::from_value(&value)?;
+    let key_id = signature::get_key_id(&headers.0)?;
+    let scheme = library::model::actor::verify_signature(
+        &activity.actor,
+        &key_id,
+        &headers.0,
+        &data,
+        settings,
+    )
+    .await?;
+    event!(Level::DEBUG, "{scheme:?} signature of {}", activity.actor);
+
+    dispatch(username, &activity, &value, settings).await?;
+    Ok(data)
+}
+
+async fn dispatch(
+    username: &str,
+    activity: &Activity,
+    value: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> library::Result<()> {
+    event!(Level::DEBUG, "dispatching {:?} activity", activity.r#type);
+    match activity.r#type {
+        ActivityType::Accept => accept::handle(username, activity, value, settings).await,
+        ActivityType::Announce => announce::handle(username, activity, value, settings).await,
+        ActivityType::Block => block::handle(username, activity, value, settings).await,
+        ActivityType::Create => create::handle(username, activity, value, settings).await,
+        ActivityType::Delete => delete::handle(username, activity, value, settings).await,
+        ActivityType::Follow => follow::handle(username, activity, value, settings).await,
+        ActivityType::Like => like::handle(username, activity, value, settings).await,
+        ActivityType::Move => r#move::handle(username, activity, value, settings).await,
+        ActivityType::Reject => reject::handle(username, activity, value, settings).await,
+        ActivityType::Undo => undo::handle(username, activity, value, settings).await,
+        ActivityType::Update => update::handle(username, activity, value, settings).await,
+        ActivityType::Unknown => {
+            event!(Level::INFO, "ignoring unsupported activity {value}");
+            Ok(())
+        }
+    }
+}
+
+fn get_activity_id(activity: &Activity) -> library::Result<&str> {
+    activity
+        .id
+        .as_deref()
+        .ok_or_else(|| Error::Parse("Missing id for activity".to_owned()))
+}
+
+fn get_object_id(activity: &Activity) -> library::Result<&str> {
+    activity
+        .object_id()
+        .ok_or_else(|| Error::Parse("Missing object id for activity".to_owned()))
+}
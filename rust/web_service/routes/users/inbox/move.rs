@@ -0,0 +1,25 @@
+use library::activitypub::activity::Activity;
+use library::settings::Settings;
+
+pub async fn handle(
+    username: &str,
+    activity: &Activity,
+    value: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> library::Result<()> {
+    if activity.target.is_none() {
+        return Err(library::Error::Parse(
+            "Missing target for Move activity".to_owned(),
+        ));
+    }
+
+    let partition = format!("users/{username}/moves");
+    library::store::put(
+        settings.store.as_ref(),
+        partition.as_str(),
+        &activity.actor,
+        value,
+    )
+    .await?;
+    Ok(())
+}
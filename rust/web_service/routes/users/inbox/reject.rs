@@ -0,0 +1,12 @@
+use library::activitypub::activity::Activity;
+use library::settings::Settings;
+
+pub async fn handle(
+    username: &str,
+    activity: &Activity,
+    _value: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> library::Result<()> {
+    let follow_id = super::get_object_id(activity)?;
+    library::model::following::reject(username, &activity.actor, follow_id, settings).await
+}
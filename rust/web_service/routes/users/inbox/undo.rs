@@ -0,0 +1,75 @@
+use library::activitypub::activity::Activity;
+use library::model::{follow_request, follower, inbox};
+use library::settings::Settings;
+use tracing::{event, Level};
+
+pub async fn handle(
+    username: &str,
+    activity: &Activity,
+    _value: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> library::Result<()> {
+    let object_id = super::get_object_id(activity)?;
+    let undone = match activity.object_type() {
+        Some("Follow") => undo_follow(username, activity, object_id, settings).await?,
+        Some("Like") => undo_activity(username, "likes", activity, object_id, settings).await?,
+        Some("Announce") => {
+            undo_activity(username, "announces", activity, object_id, settings).await?
+        }
+        // a bare id may reference any of the activities we keep
+        None => {
+            undo_follow(username, activity, object_id, settings).await?
+                || undo_activity(username, "likes", activity, object_id, settings).await?
+                || undo_activity(username, "announces", activity, object_id, settings).await?
+        }
+        Some(_) => false,
+    };
+    if !undone {
+        event!(
+            Level::INFO,
+            "ignoring undo of {:?} {object_id} for {username}",
+            activity.object_type()
+        );
+    }
+    Ok(())
+}
+
+/// Removes the follower, or its pending request, if it was made by the undone `Follow`.
+async fn undo_follow(
+    username: &str,
+    activity: &Activity,
+    follow_id: &str,
+    settings: &rocket::State<Settings>,
+) -> library::Result<bool> {
+    if let Some(follow_actor) = activity.object["actor"].as_str() {
+        if follow_actor != activity.actor {
+            return Err(library::Error::Invalid(format!(
+                "{} can't undo a Follow by {follow_actor}",
+                activity.actor
+            )));
+        }
+    }
+
+    if follow_request::withdraw(username, &activity.actor, follow_id, settings).await? {
+        return Ok(true);
+    }
+
+    // the follower is kept under the actor that sent the Follow, so only its id can differ
+    let follow = follower::get(username, &activity.actor, settings).await?;
+    if !follow.is_some_and(|follow| follow["id"] == follow_id) {
+        return Ok(false);
+    }
+
+    follower::delete(username, &activity.actor, settings).await?;
+    Ok(true)
+}
+
+async fn undo_activity(
+    username: &str,
+    collection: &str,
+    activity: &Activity,
+    activity_id: &str,
+    settings: &rocket::State<Settings>,
+) -> library::Result<bool> {
+    inbox::delete_activity(username, collection, &activity.actor, activity_id, settings).await
+}
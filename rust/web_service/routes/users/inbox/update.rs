@@ -0,0 +1,12 @@
+use library::activitypub::activity::Activity;
+use library::settings::Settings;
+
+/// An update replaces the object previously stored by a create of the same actor.
+pub async fn handle(
+    username: &str,
+    activity: &Activity,
+    value: &serde_json::Value,
+    settings: &rocket::State<Settings>,
+) -> library::Result<()> {
+    super::create::handle(username, activity, value, settings).await
+}
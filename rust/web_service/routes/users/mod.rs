@@ -1,5 +1,4 @@
-use library::activitypub::object::{Object, PublicKey};
-use library::rsa;
+use library::Error;
 use rocket::http::ContentType;
 
 mod followers;
@@ -12,7 +11,9 @@ pub fn routes() -> Vec<rocket::Route> {
     rocket::routes![
         handler,
         followers::handler,
+        followers::page,
         following::handler,
+        following::page,
         inbox::handler,
         outbox::handler,
         outbox::page,
@@ -29,52 +30,14 @@ pub struct UserResponse(String, ContentType);
 pub async fn handler(
     username: &str,
     settings: &rocket::State<library::settings::Settings>,
-) -> Option<UserResponse> {
-    if let Some(user) = library::model::user::get(username, settings).await {
-        let public_key = rsa::der_to_pem(user.public_key.as_ref().unwrap());
-        let user_uri = format!("{}/users/{username}", settings.base_url);
+) -> Result<UserResponse, Error> {
+    if let Some(user) = library::model::user::get(username, settings).await? {
+        let actor = library::model::user::get_actor(username, &user, &settings.base_url)?;
         let content_type =
             ContentType::new("application", "activity+json").with_params(("charset", "utf-8"));
-        let body = serde_json::json!(Object {
-            actor: None,
-            atom_uri: None,
-            attachment: Some(Vec::new()),
-            attributed_to: None,
-            cc: None,
-            content: None,
-            context: library::activitypub::context::default(),
-            conversation: None,
-            devices: Some(format!("{user_uri}/collections/devices")),
-            discoverable: Some(false),
-            followers: Some(format!("{user_uri}/followers")),
-            following: Some(format!("{user_uri}/following")),
-            id: Some(user_uri.clone()),
-            in_reply_to: None,
-            in_reply_to_atom_uri: None,
-            inbox: Some(format!("{user_uri}/inbox")),
-            manually_approves_followers: Some(false),
-            name: Some(username.to_owned()),
-            object: None,
-            outbox: Some(format!("{user_uri}/outbox")),
-            partition_key: None,
-            preferred_username: Some(username.to_owned()),
-            public_key: Some(PublicKey {
-                id: format!("{user_uri}#main-key"),
-                owner: user_uri,
-                public_key_pem: public_key,
-            }),
-            published: Some(user.get_published_time()),
-            r#type: Some("Person".to_owned()),
-            sensitive: None,
-            sort_key: None,
-            summary: Some(String::new()),
-            tag: Some(Vec::new()),
-            to: None,
-            url: Some(format!("{}/@{username}", settings.base_url)),
-            extra: serde_json::Value::Null,
-        });
-        return Some(UserResponse(body.to_string(), content_type));
+        let body = serde_json::json!(actor);
+        return Ok(UserResponse(body.to_string(), content_type));
     }
 
-    None
+    Err(Error::NotFound(format!("user {username}")))
 }
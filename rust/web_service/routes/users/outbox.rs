@@ -1,79 +1,53 @@
-use aws_sdk_dynamodb::types::AttributeValue;
-use library::{activitypub::object::Object, settings::Settings};
+use library::{activitypub::activity, model::status, settings::Settings, Error};
 use rocket::http::ContentType;
 use rocket::serde::json::Json;
+use tracing::{event, Level};
 
 #[derive(rocket::Responder)]
 pub struct Outbox(Json<serde_json::Value>, ContentType);
 
+const PAGE_SIZE: usize = 20;
+
 #[rocket::get("/users/<username>/outbox")]
-pub async fn handler(username: &str, settings: &rocket::State<Settings>) -> Outbox {
-    let partition = format!("users/{username}/statuses");
-    let response = settings
-        .db_client
-        .query()
-        .table_name(&settings.table_name)
-        .key_condition_expression("#partition_key = :valueToMatch")
-        .expression_attribute_names(
-            "#partition_key",
-            library::dynamodb::PARTITION_KEY_NAME.to_owned(),
-        )
-        .expression_attribute_values(":valueToMatch", AttributeValue::S(partition))
-        .select(aws_sdk_dynamodb::types::Select::Count)
-        .send()
-        .await
-        .unwrap();
+pub async fn handler(username: &str, settings: &rocket::State<Settings>) -> Result<Outbox, Error> {
+    let total_items = status::count(username, settings).await?;
     let id = format!("{}/users/{username}/outbox", settings.base_url);
     let body = Json(serde_json::json!({
         "@context": "https://www.w3.org/ns/activitystreams",
         "id": id,
         "type": "OrderedCollection",
-        "totalItems": response.count(),
+        "totalItems": total_items,
         "first": format!("{id}?page=true"),
         "last": format!("{id}?min_id=0&page=true"),
     }));
     let content_type =
         ContentType::new("application", "activity+json").with_params([("charset", "utf-8")]);
-    Outbox(body, content_type)
+    Ok(Outbox(body, content_type))
 }
 
-#[rocket::get("/users/<username>/outbox?<page>")]
-pub async fn page(username: &str, page: &str, settings: &rocket::State<Settings>) -> Outbox {
-    let partition = format!("users/{username}/statuses");
-    let response = settings
-        .db_client
-        .query()
-        .table_name(&settings.table_name)
-        .key_condition_expression("#partition_key = :valueToMatch")
-        .expression_attribute_names(
-            "#partition_key",
-            library::dynamodb::PARTITION_KEY_NAME.to_owned(),
-        )
-        .expression_attribute_values(":valueToMatch", AttributeValue::S(partition))
-        .limit(20)
-        .scan_index_forward(false)
-        .send()
-        .await
-        .unwrap();
-    let items = response.items().unwrap();
-    let body: Vec<Object> = serde_dynamo::from_items(items.to_vec()).unwrap();
-    let body = body
-        .into_iter()
-        .map(|object| {
-            serde_json::json!({
-                    "@context": "https://www.w3.org/ns/activitystreams",
-                    "id": format!("{}/activity", object.id.as_ref().unwrap()),
-                    "type": "Create",
-                    "actor": object.actor.as_ref().unwrap(),
-                    "published": object.published.as_ref().unwrap(),
-                    "to": object.to.as_ref().unwrap(),
-                    "cc": object.cc.as_ref().unwrap(),
-                    "object": &object
-            })
-        })
-        .collect::<Vec<serde_json::Value>>();
+#[rocket::get("/users/<username>/outbox?<page>&<max_id>&<min_id>&<since_id>")]
+pub async fn page(
+    username: &str,
+    page: &str,
+    max_id: Option<u64>,
+    min_id: Option<u64>,
+    since_id: Option<u64>,
+    settings: &rocket::State<Settings>,
+) -> Result<Outbox, Error> {
+    let range = status::Range {
+        max_id,
+        min_id,
+        since_id,
+    };
+    event!(Level::DEBUG, username, page, ?range);
+    let statuses = status::get_page(username, range, PAGE_SIZE, settings).await?;
+    let body = statuses
+        .items
+        .iter()
+        .map(activity::create)
+        .collect::<Result<Vec<serde_json::Value>, Error>>()?;
     let id = format!("{}/users/{username}/outbox", settings.base_url);
-    let body = Json(serde_json::json!({
+    let mut body = serde_json::json!({
         "@context":[
             "https://www.w3.org/ns/activitystreams",
             {
@@ -92,14 +66,30 @@ pub async fn page(username: &str, page: &str, settings: &rocket::State<Settings>
               "Hashtag": "as:Hashtag"
             }
           ],
-        "id": format!("{id}?page={page}"),
+        "id": page_url(&id, range, page),
         "type": "OrderedCollectionPage",
-        "next": format!("{id}?max_id=109635460763016637&page=true"),
-        "prev": format!("{id}?min_id=109757651111534104&page=true"),
         "partOf": id,
         "orderedItems": body
-    }));
+    });
+    if let Some(next) = statuses.next {
+        body["next"] = serde_json::Value::String(format!("{id}?max_id={next}&page=true"));
+    }
+    if let Some(prev) = statuses.prev {
+        body["prev"] = serde_json::Value::String(format!("{id}?min_id={prev}&page=true"));
+    }
     let content_type =
         ContentType::new("application", "activity+json").with_params([("charset", "utf-8")]);
-    Outbox(body, content_type)
+    Ok(Outbox(Json(body), content_type))
+}
+
+fn page_url(id: &str, range: status::Range, page: &str) -> String {
+    let bounds = [
+        ("max_id", range.max_id),
+        ("min_id", range.min_id),
+        ("since_id", range.since_id),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| value.map(|value| format!("{name}={value}&")))
+    .collect::<String>();
+    format!("{id}?{bounds}page={page}")
 }
@@ -10,23 +10,23 @@ pub struct Webfinger(Json<serde_json::Value>, ContentType);
 pub async fn handler(
     resource: &str,
     settings: &rocket::State<library::settings::Settings>,
-) -> Option<Webfinger> {
+) -> Result<Option<Webfinger>, library::Error> {
     let split = resource.splitn(2, ':').collect::<Vec<&str>>();
     event!(Level::DEBUG, "{:?}", split);
     if split[0] != "acct" || split.len() < 2 {
-        return None;
+        return Ok(None);
     }
     let sub_split = split[1].split('@').collect::<Vec<&str>>();
     if sub_split.len() != 2 {
-        return None;
+        return Ok(None);
     }
     let username = sub_split[0];
     let domain = sub_split[1];
     if domain != settings.domain_name {
-        return None;
+        return Ok(None);
     }
 
-    if (user::get(username, settings).await).is_some() {
+    if user::get(username, settings).await?.is_some() {
         let body = Json(serde_json::json!({
           "subject": resource,
           "links": [{
@@ -37,8 +37,8 @@ pub async fn handler(
         }));
         let content_type =
             ContentType::new("application", "jrd+json").with_params([("charset", "utf-8")]);
-        return Some(Webfinger(body, content_type));
+        return Ok(Some(Webfinger(body, content_type)));
     }
 
-    None
+    Ok(None)
 }
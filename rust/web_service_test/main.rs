@@ -3,6 +3,7 @@ use tracing::{event, Level};
 
 use library::dynamodb;
 use library::model::user::User;
+use library::store::DynamoDbStore;
 use std::env;
 use std::fs;
 
@@ -46,19 +47,27 @@ async fn main() {
     let signer_url = add_protocol(signer_domain.as_str());
     let signature_key_id = format!("{signer_url}/users/{signer_username}#main-key");
 
-    let db_client = dynamodb::get_client().await;
+    let store = DynamoDbStore::new(dynamodb::get_client().await, table_name.to_owned());
     if target_domain.starts_with("localhost") {
-        dynamodb::create_table_if_not_exists(&db_client, table_name).await;
-        library::model::user::create(&db_client, table_name, target_username.as_str()).await;
+        dynamodb::create_table_if_not_exists(store.client(), table_name)
+            .await
+            .unwrap();
+        library::model::user::create(&store, target_username.as_str())
+            .await
+            .unwrap();
     }
     let signer: User = if signer_domain.starts_with("localhost") {
-        dynamodb::create_table_if_not_exists(&db_client, table_name).await;
-        library::model::user::create(&db_client, table_name, signer_username.as_str()).await
+        dynamodb::create_table_if_not_exists(store.client(), table_name)
+            .await
+            .unwrap();
+        library::model::user::create(&store, signer_username.as_str())
+            .await
+            .unwrap()
     } else {
-        let get_item_output =
-            library::model::user::get_item(signer_username.as_str(), &db_client, table_name).await;
-        let item = get_item_output.item.unwrap();
-        serde_dynamo::from_item(item).unwrap()
+        library::model::user::load(signer_username.as_str(), &store)
+            .await
+            .unwrap()
+            .unwrap()
     };
 
     let paths = std::fs::read_dir("./test-cases").unwrap();
@@ -110,7 +119,8 @@ async fn main() {
                         signer.private_key.as_ref().unwrap(),
                         signature_key_id.as_str(),
                         &time::OffsetDateTime::UNIX_EPOCH,
-                    );
+                    )
+                    .unwrap();
                 }
                 event!(
                     Level::DEBUG,